@@ -0,0 +1,117 @@
+// One-dimensional elementary cellular automata. A row of cells, each alive
+// or dead, steps to the next generation by looking up each cell and its two
+// neighbors in a Wolfram rule number: the next state of a cell whose
+// neighborhood, read left to right as a 3-bit number, is n is bit n of the
+// rule. What lies past the ends of the row is decided by a `Boundary`.
+//
+// Pull it into a day with:
+//
+//     #[path = "../../common/automaton.rs"]
+//     mod automaton;
+
+#![allow(dead_code)]
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Boundary {
+    // Everything past the ends is dead.
+    Dead,
+    // The row is a ring, so the first cell's left neighbor is the last cell.
+    Wrap,
+    // Each end cell is its own outside neighbor.
+    Reflect,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Automaton {
+    pub rule: u8,
+    pub boundary: Boundary,
+}
+
+impl Automaton {
+    pub fn new(rule: u8, boundary: Boundary) -> Automaton {
+        Automaton {
+            rule: rule,
+            boundary: boundary,
+        }
+    }
+
+    // `neighborhood` holds the left cell in bit 2, the cell itself in bit 1
+    // and the right cell in bit 0.
+    pub fn next_state(&self, neighborhood: u8) -> bool {
+        (self.rule >> (neighborhood & 0b111)) & 1 == 1
+    }
+
+    pub fn step(&self, cells: &[bool]) -> Vec<bool> {
+        let len = cells.len();
+        let outside = |idx: Option<usize>, edge: usize, wrapped: usize| {
+            match (idx, self.boundary) {
+                (Some(idx), _) => cells[idx],
+                (None, Boundary::Dead) => false,
+                (None, Boundary::Wrap) => cells[wrapped],
+                (None, Boundary::Reflect) => cells[edge],
+            }
+        };
+
+        (0..len)
+            .map(|idx| {
+                let left = outside(idx.checked_sub(1), 0, len - 1);
+                let right = outside(Some(idx + 1).filter(|next| *next < len),
+                    len - 1, 0);
+                let neighborhood = ((left as u8) << 2)
+                    | ((cells[idx] as u8) << 1)
+                    | (right as u8);
+                self.next_state(neighborhood)
+            })
+            .collect()
+    }
+
+    // `num_gens` generations, starting with `first`.
+    pub fn run(&self, first: &[bool], num_gens: usize) -> Vec<Vec<bool>> {
+        let mut gens = Vec::with_capacity(num_gens);
+        if num_gens == 0 {
+            return gens;
+        }
+
+        gens.push(first.to_vec());
+        while gens.len() < num_gens {
+            let next = self.step(gens.last().unwrap());
+            gens.push(next);
+        }
+
+        gens
+    }
+}
+
+// One line per generation, drawing live cells with `alive` and dead ones
+// with `dead`.
+pub fn render_text(gens: &[Vec<bool>], alive: char, dead: char) -> String {
+    let mut s = String::new();
+    for cells in gens {
+        s.extend(cells.iter().map(|cell| if *cell { alive } else { dead }));
+        s.push('\n');
+    }
+
+    s
+}
+
+// Binary PBM (P4) image with one pixel per cell, black for live cells, and
+// one pixel row per generation.
+pub fn render_pbm(gens: &[Vec<bool>]) -> Vec<u8> {
+    let width = gens.first().map_or(0, |cells| cells.len());
+
+    let mut image = format!("P4\n{} {}\n", width, gens.len()).into_bytes();
+    for cells in gens {
+        // Each pixel row is padded out to a whole number of bytes.
+        for byte_cells in cells.chunks(8) {
+            let mut byte = 0u8;
+            for (bit, cell) in byte_cells.iter().enumerate() {
+                if *cell {
+                    byte |= 0x80 >> bit;
+                }
+            }
+            image.push(byte);
+        }
+    }
+
+    image
+}
@@ -0,0 +1,190 @@
+// Parser for the `df -h` listing of the grid computing cluster's storage
+// nodes, shared by both halves of the day. Coordinates are usize, so a
+// cluster of any width parses, and sizes are kept in megabytes.
+//
+// Pull it into a day with:
+//
+//     #[path = "../../common/df.rs"]
+//     mod df;
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Coord {
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Node {
+    pub pos: Coord,
+    pub size: u64,
+    pub used: u64,
+    pub avail: u64,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line_num: Option<usize>,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line_num {
+            Some(line_num) => write!(f, "line {}: {}", line_num, self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+pub const DF_HEADER: [&str; 5] =
+    ["Filesystem", "Size", "Used", "Avail", "Use%"];
+
+// Sizes are kept in megabytes so that T, G and M columns can be mixed.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let unit = s.chars().last().ok_or("empty size")?;
+    let scale = match unit {
+        'T' => 1024 * 1024,
+        'G' => 1024,
+        'M' => 1,
+        _ => return Err(format!("size '{}' has no T, G or M unit", s)),
+    };
+
+    let digits = &s[..(s.len() - unit.len_utf8())];
+    u64::from_str(digits).ok()
+        .and_then(|val| val.checked_mul(scale))
+        .ok_or(format!("bad size '{}'", s))
+}
+
+// The largest unit that shows `megs` exactly.
+pub fn format_size(megs: u64) -> String {
+    if megs > 0 && megs.is_multiple_of(1024 * 1024) {
+        format!("{}T", megs / (1024 * 1024))
+    } else if megs > 0 && megs.is_multiple_of(1024) {
+        format!("{}G", megs / 1024)
+    } else {
+        format!("{}M", megs)
+    }
+}
+
+// Parses one coordinate of a node name, such as the "x12" in
+// "/dev/grid/node-x12-y3". A number too big for a usize is reported as out
+// of range rather than as a malformed name.
+fn parse_coord(part: &str, axis: char, name: &str) -> Result<usize, String> {
+    let digits = part.strip_prefix(axis)
+        .filter(|digits| !digits.is_empty()
+            && digits.chars().all(|c| c.is_ascii_digit()))
+        .ok_or_else(|| format!("bad node name '{}'", name))?;
+
+    usize::from_str(digits).map_err(|_| {
+        format!("{} coordinate {} of node '{}' is out of range", axis, digits,
+            name)
+    })
+}
+
+// Parses a line such as "/dev/grid/node-x0-y0   85T   72T   13T   84%".
+pub fn parse_node(s: &str) -> Result<(Coord, Node), String> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    if parts.len() != DF_HEADER.len() {
+        return Err(format!("expected {} columns, found {}", DF_HEADER.len(),
+            parts.len()));
+    }
+
+    let (x_str, y_str) = parts[0].strip_prefix("/dev/grid/node-")
+        .and_then(|name| name.split_once('-'))
+        .ok_or_else(|| format!("bad node name '{}'", parts[0]))?;
+    let x = parse_coord(x_str, 'x', parts[0])?;
+    let y = parse_coord(y_str, 'y', parts[0])?;
+
+    let size = parse_size(parts[1])?;
+    let used = parse_size(parts[2])?;
+    let avail = parse_size(parts[3])?;
+    let use_pct = parts[4].strip_suffix('%')
+        .and_then(|pct| u64::from_str(pct).ok())
+        .ok_or(format!("bad percentage '{}'", parts[4]))?;
+
+    if used.checked_add(avail) != Some(size) {
+        return Err(format!("size {} isn't used {} plus avail {}",
+            parts[1], parts[2], parts[3]));
+    }
+
+    // df rounds the percentage, and not always in the same direction.
+    let exact_pct = if size == 0 { 0.0 } else {
+        used as f64 * 100.0 / size as f64
+    };
+    if use_pct != exact_pct.floor() as u64
+        && use_pct != exact_pct.ceil() as u64 {
+
+        return Err(format!("use {}% doesn't match {} of {}", use_pct,
+            parts[2], parts[1]));
+    }
+
+    let node = Node {
+        pos: Coord { x: x, y: y },
+        size: size,
+        used: used,
+        avail: avail,
+    };
+
+    Ok((node.pos, node))
+}
+
+// Parses the output of `df -h` on the grid nodes. Anything before the column
+// headers, like the shell prompt, is skipped. Every node from x0-y0 to the
+// largest x and y must be listed exactly once.
+pub fn parse_nodes(input: &str) -> Result<HashMap<Coord, Node>, ParseError> {
+    let mut nodes = HashMap::new();
+    let mut node_lines = HashMap::new();
+    let mut seen_header = false;
+
+    for (line_idx, line) in input.lines().enumerate() {
+        let line_num = line_idx + 1;
+        let err = |msg| ParseError { line_num: Some(line_num), msg: msg };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if !seen_header {
+            if line.starts_with(DF_HEADER[0]) {
+                if !line.split_whitespace().eq(DF_HEADER.iter().cloned()) {
+                    return Err(err(format!("unexpected header '{}'", line)));
+                }
+                seen_header = true;
+            }
+            continue;
+        }
+
+        let (pos, node) = parse_node(line).map_err(err)?;
+        if let Some(first_line) = node_lines.insert(pos, line_num) {
+            return Err(err(format!("node x{}-y{} already listed on line {}",
+                pos.x, pos.y, first_line)));
+        }
+        nodes.insert(pos, node);
+    }
+
+    let no_line_err = |msg| ParseError { line_num: None, msg: msg };
+    if !seen_header {
+        return Err(no_line_err("no df header".to_string()));
+    }
+
+    let max_x = nodes.keys().map(|pos| pos.x).max()
+        .ok_or(no_line_err("no nodes".to_string()))?;
+    let max_y = nodes.keys().map(|pos| pos.y).max().unwrap();
+    for y in 0..=max_y {
+        for x in 0..=max_x {
+            if !nodes.contains_key(&Coord { x: x, y: y }) {
+                return Err(no_line_err(format!("missing node x{}-y{}", x,
+                    y)));
+            }
+        }
+    }
+
+    Ok(nodes)
+}
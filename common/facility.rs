@@ -0,0 +1,977 @@
+// The radioisotope testing facility: parsing the facility spec and the floor
+// descriptions, and an A* search for the fewest elevator trips that bring
+// every item to the top floor without frying a microchip. Solutions can be
+// printed and replayed against the rules by `validate_solution`.
+//
+// Pull it into a day with:
+//
+//     #[path = "../../common/facility.rs"]
+//     mod facility;
+
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+pub const SPEC_FILE: &str = "facility.cfg";
+pub const INPUT_FILE: &str = "input.txt";
+
+const FLOOR_ORDINALS: [&str; 10] = [
+    "first", "second", "third", "fourth", "fifth",
+    "sixth", "seventh", "eighth", "ninth", "tenth",
+];
+
+// The rules an item kind plays by. A radiating item (a generator) fries any
+// vulnerable item (a chip) of another element on the same floor, unless that
+// item's own radiating partner is also there to protect it.
+#[derive(Clone, Debug)]
+pub struct ItemKind {
+    pub name: String,
+    pub symbol: char,
+    pub radiates: bool,
+    pub vulnerable: bool,
+}
+
+// Everything about the facility that isn't the position of the items.
+#[derive(Debug)]
+pub struct FacilitySpec {
+    pub num_floors: usize,
+    pub elevator_capacity: usize,
+    pub item_kinds: Vec<ItemKind>,
+}
+
+impl FacilitySpec {
+    fn default_item_kinds() -> Vec<ItemKind> {
+        vec![
+            ItemKind {
+                name: "microchip".to_string(),
+                symbol: 'C',
+                radiates: false,
+                vulnerable: true,
+            },
+            ItemKind {
+                name: "generator".to_string(),
+                symbol: 'G',
+                radiates: true,
+                vulnerable: false,
+            },
+        ]
+    }
+
+    pub fn find_item_kind(&self, name: &str) -> Option<usize> {
+        self.item_kinds.iter().position(|kind| kind.name == name)
+    }
+}
+
+// Sorted by element first so a floor lists each element's items together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Item {
+    pub element: u32,
+    pub kind: usize,
+}
+
+pub struct ItemDisp<'a>(&'a FacilitySpec, &'a Item);
+
+impl<'a> fmt::Display for ItemDisp<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.0.item_kinds[self.1.kind].symbol, self.1.element)
+    }
+}
+
+pub type Floor = Vec<Item>;
+// struct FloorDisp<'a>(&'a Floor);
+
+// impl<'a> fmt::Display for FloorDisp<'a> {
+//     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+//         f.write_str(&self.0.iter()
+//             .map(|item| format!("{}", item))
+//             .collect::<Vec<String>>()
+//             .join(", "))
+//     }
+// }
+
+#[derive(Clone)]
+pub struct Facility {
+    pub spec: Rc<FacilitySpec>,
+    pub elev_floor: usize,
+    pub floors: Vec<Floor>,
+}
+
+impl fmt::Display for Facility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let num_floors = self.floors.len();
+        for i in 0..num_floors {
+            let floor = num_floors - i - 1;
+            let on_cur_floor = floor == self.elev_floor;
+            write!(f, "{}", if on_cur_floor { "E" } else { " " })?;
+            for item in self.floors[floor].iter() {
+                write!(f, " {}", ItemDisp(&self.spec, item))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// One elevator trip: the items carried and the floors it went between.
+#[derive(Clone, Debug)]
+pub struct Move {
+    pub from_floor: usize,
+    pub to_floor: usize,
+    pub items: Vec<Item>,
+}
+
+pub struct MoveDisp<'a>(&'a FacilitySpec, &'a Move);
+
+impl<'a> fmt::Display for MoveDisp<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = self.1;
+        write!(f, "{} -> {}:", m.from_floor + 1, m.to_floor + 1)?;
+        for item in m.items.iter() {
+            write!(f, " {}", ItemDisp(self.0, item))?;
+        }
+        Ok(())
+    }
+}
+
+// States live in an arena so each one can point back at the state it was
+// reached from. `parent` and `last_move` are None only for the initial state.
+#[derive(Clone)]
+pub struct State {
+    pub cur_move: u32,
+    pub facility: Facility,
+    pub parent: Option<usize>,
+    pub last_move: Option<Move>,
+}
+
+// `facilities[0]` is the starting layout and `facilities[i + 1]` is the
+// layout after `moves[i]`.
+pub struct Solution {
+    pub num_moves: u32,
+    pub moves: Vec<Move>,
+    pub facilities: Vec<Facility>,
+}
+
+// Entry in the A* open set. Ordered so that BinaryHeap (a max-heap) pops the
+// lowest estimated total cost first, preferring deeper states on ties.
+#[derive(PartialEq, Eq)]
+struct OpenEntry {
+    est_total: u32,
+    cur_move: u32,
+    state_idx: usize,
+}
+
+impl Ord for OpenEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.est_total.cmp(&self.est_total)
+            .then_with(|| self.cur_move.cmp(&other.cur_move))
+            .then_with(|| other.state_idx.cmp(&self.state_idx))
+    }
+}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reads the optional spec file. Each non-blank line that doesn't start with
+// '#' is `key = value`:
+//
+//     floors = 5
+//     elevator_capacity = 3
+//     item = microchip C vulnerable
+//     item = generator G radiates
+//     item = shielded-microchip S
+//
+// Any `item` line replaces the default microchip/generator pair with the
+// listed kinds. Without a `floors` line the facility is as tall as the
+// highest floor the input describes, which is recorded here as 0 floors until
+// the input has been read.
+pub fn parse_spec(config: &str) -> Result<FacilitySpec, ParseError> {
+    let mut spec = FacilitySpec {
+        num_floors: 0,
+        elevator_capacity: 2,
+        item_kinds: vec![],
+    };
+
+    for (i, raw_line) in config.lines().enumerate() {
+        let line_num = i + 1;
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |word: &str, msg: String| {
+            ParseError::at_word(SPEC_FILE, line_num, raw_line, word, msg)
+        };
+
+        let (key, value) = match line.find('=') {
+            Some(idx) => (line[..idx].trim(), line[(idx + 1)..].trim()),
+            None => return Err(ParseError::whole_line(SPEC_FILE, line_num,
+                raw_line, "expected key = value".to_string())),
+        };
+
+        match key {
+            "floors" => {
+                spec.num_floors = value.parse().map_err(|_| {
+                    error(value, format!("bad floor count: {}", value))
+                })?;
+            },
+            "elevator_capacity" => {
+                spec.elevator_capacity = value.parse().map_err(|_| {
+                    error(value, format!("bad elevator capacity: {}", value))
+                })?;
+                if spec.elevator_capacity == 0 {
+                    return Err(error(value,
+                        "elevator capacity must be at least 1".to_string()));
+                }
+            },
+            "item" => {
+                let parts = value.split_whitespace().collect::<Vec<&str>>();
+                if parts.len() < 2 || parts[1].chars().count() != 1 {
+                    return Err(error(value,
+                        "expected item = <name> <symbol> [flags]".to_string()));
+                }
+                let mut kind = ItemKind {
+                    name: parts[0].to_string(),
+                    symbol: parts[1].chars().next().unwrap(),
+                    radiates: false,
+                    vulnerable: false,
+                };
+                for flag in parts[2..].iter() {
+                    match *flag {
+                        "radiates" => kind.radiates = true,
+                        "vulnerable" => kind.vulnerable = true,
+                        bad_flag => return Err(error(bad_flag,
+                            format!("bad item flag: {}", bad_flag))),
+                    }
+                }
+                spec.item_kinds.push(kind);
+            },
+            bad_key => return Err(error(bad_key,
+                format!("bad key: {}", bad_key))),
+        }
+    }
+
+    if spec.item_kinds.is_empty() {
+        spec.item_kinds = FacilitySpec::default_item_kinds();
+    }
+
+    Ok(spec)
+}
+
+fn get_item_type_num(
+    type_str: &str, type_map: &mut HashMap<String, u32>) -> u32 {
+
+    if let Some(val) = type_map.get(type_str) {
+        return *val;
+    }
+
+    let new_val = (type_map.len() + 1) as u32;
+    type_map.insert(type_str.to_string(), new_val);
+    new_val
+}
+
+fn sort_floor_items(floor: &mut Floor) {
+    floor.sort();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TokenKind {
+    Word,
+    Comma,
+    Period,
+}
+
+#[derive(Debug)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    col: usize,
+}
+
+// A line of the spec file or the input that doesn't fit the grammar. `col`
+// and `len` are the byte range of the offending word within `line` (or its
+// end, if the line stops too early).
+#[derive(Debug)]
+pub struct ParseError {
+    pub file: &'static str,
+    pub line_num: usize,
+    pub line: String,
+    pub col: usize,
+    pub len: usize,
+    pub msg: String,
+}
+
+impl ParseError {
+    // An error about a whole line rather than one word of it.
+    fn whole_line(file: &'static str, line_num: usize, line: &str,
+        msg: String) -> ParseError {
+
+        ParseError {
+            file: file,
+            line_num: line_num,
+            line: line.to_string(),
+            col: line.len() - line.trim_start().len(),
+            len: line.trim().len(),
+            msg: msg,
+        }
+    }
+
+    // An error about `word`, which must be a slice of `line`.
+    fn at_word(file: &'static str, line_num: usize, line: &str, word: &str,
+        msg: String) -> ParseError {
+
+        ParseError {
+            file: file,
+            line_num: line_num,
+            line: line.to_string(),
+            col: word.as_ptr() as usize - line.as_ptr() as usize,
+            len: word.len(),
+            msg: msg,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}:{}:{}: {}",
+            self.file, self.line_num, self.col + 1, self.msg)?;
+        writeln!(f, "    {}", self.line)?;
+        write!(f, "    {}{}", " ".repeat(self.col), "^".repeat(self.len.max(1)))
+    }
+}
+
+fn tokenize<'a>(line: &'a str) -> Vec<Token<'a>> {
+    let mut tokens = vec![];
+    let mut word_start: Option<usize> = None;
+
+    for (idx, c) in line.char_indices() {
+        let punct = match c {
+            ',' => Some(TokenKind::Comma),
+            '.' => Some(TokenKind::Period),
+            _ => None,
+        };
+
+        if c.is_whitespace() || punct.is_some() {
+            if let Some(start) = word_start.take() {
+                tokens.push(Token {
+                    kind: TokenKind::Word,
+                    text: &line[start..idx],
+                    col: start,
+                });
+            }
+            if let Some(kind) = punct {
+                tokens.push(Token {
+                    kind: kind,
+                    text: &line[idx..(idx + 1)],
+                    col: idx,
+                });
+            }
+        } else if word_start.is_none() {
+            word_start = Some(idx);
+        }
+    }
+
+    if let Some(start) = word_start {
+        tokens.push(Token {
+            kind: TokenKind::Word,
+            text: &line[start..],
+            col: start,
+        });
+    }
+
+    tokens
+}
+
+// Recursive-descent parser for one line of the puzzle input:
+//
+//     line  := "The" ORDINAL "floor" "contains" items "."
+//     items := "nothing" "relevant"
+//            | item ( ( "," [ "and" ] | "and" ) item )*
+//     item  := ( "a" | "an" ) ELEMENT[-compatible] KIND
+//
+// Keywords are matched case-insensitively. KIND is any item kind named in the
+// facility spec, and ELEMENT is any word.
+struct FloorParser<'a> {
+    line: &'a str,
+    line_num: usize,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> FloorParser<'a> {
+    fn new(line: &'a str, line_num: usize) -> FloorParser<'a> {
+        FloorParser {
+            line: line,
+            line_num: line_num,
+            tokens: tokenize(line),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn error(&self, msg: String) -> ParseError {
+        let (col, len) = match self.peek() {
+            Some(token) => (token.col, token.text.len()),
+            None => (self.line.trim_end().len(), 1),
+        };
+        let found = match self.peek() {
+            Some(token) => format!("'{}'", token.text),
+            None => "end of line".to_string(),
+        };
+
+        ParseError {
+            file: INPUT_FILE,
+            line_num: self.line_num,
+            line: self.line.to_string(),
+            col: col,
+            len: len,
+            msg: format!("{}, found {}", msg, found),
+        }
+    }
+
+    fn next_is_word(&self, word: &str) -> bool {
+        match self.peek() {
+            Some(token) => token.kind == TokenKind::Word &&
+                token.text.eq_ignore_ascii_case(word),
+            None => false,
+        }
+    }
+
+    fn next_is(&self, kind: TokenKind) -> bool {
+        match self.peek() {
+            Some(token) => token.kind == kind,
+            None => false,
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), ParseError> {
+        if !self.next_is_word(word) {
+            return Err(self.error(format!("expected '{}'", word)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn any_word(&mut self, what: &str) -> Result<&'a str, ParseError> {
+        if !self.next_is(TokenKind::Word) {
+            return Err(self.error(format!("expected {}", what)));
+        }
+        let text = self.tokens[self.pos].text;
+        self.pos += 1;
+        Ok(text)
+    }
+
+    fn parse_ordinal(&mut self) -> Result<usize, ParseError> {
+        let floor_idx = match self.peek() {
+            Some(token) if token.kind == TokenKind::Word =>
+                FLOOR_ORDINALS.iter()
+                    .position(|ord| token.text.eq_ignore_ascii_case(ord)),
+            _ => None,
+        };
+
+        match floor_idx {
+            Some(idx) => {
+                self.pos += 1;
+                Ok(idx)
+            },
+            None => Err(self.error(format!(
+                "expected a floor ordinal ({} to {})",
+                FLOOR_ORDINALS[0], FLOOR_ORDINALS[FLOOR_ORDINALS.len() - 1]))),
+        }
+    }
+
+    fn parse_item(&mut self, spec: &FacilitySpec,
+        type_map: &mut HashMap<String, u32>) -> Result<Item, ParseError> {
+
+        if self.next_is_word("an") {
+            self.pos += 1;
+        } else {
+            self.expect_word("a")?;
+        }
+
+        let element = self.any_word("an element name")?;
+        let element = element.trim_end_matches("-compatible");
+
+        let kind = match self.peek() {
+            Some(token) if token.kind == TokenKind::Word =>
+                spec.find_item_kind(token.text),
+            _ => None,
+        };
+        let kind = match kind {
+            Some(kind) => kind,
+            None => {
+                let kind_names = spec.item_kinds.iter()
+                    .map(|kind| kind.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ");
+                return Err(self.error(
+                    format!("expected an item kind ({})", kind_names)));
+            },
+        };
+        self.pos += 1;
+
+        Ok(Item {
+            element: get_item_type_num(element, type_map),
+            kind: kind,
+        })
+    }
+
+    fn parse_items(&mut self, spec: &FacilitySpec,
+        type_map: &mut HashMap<String, u32>) -> Result<Floor, ParseError> {
+
+        let mut floor = Floor::new();
+
+        if self.next_is_word("nothing") {
+            self.pos += 1;
+            self.expect_word("relevant")?;
+            return Ok(floor);
+        }
+
+        floor.push(self.parse_item(spec, type_map)?);
+        loop {
+            if self.next_is(TokenKind::Comma) {
+                self.pos += 1;
+                if self.next_is_word("and") {
+                    self.pos += 1;
+                }
+            } else if self.next_is_word("and") {
+                self.pos += 1;
+            } else if self.next_is(TokenKind::Period) {
+                break;
+            } else {
+                return Err(
+                    self.error("expected ',', 'and' or '.'".to_string()));
+            }
+
+            floor.push(self.parse_item(spec, type_map)?);
+        }
+
+        Ok(floor)
+    }
+
+    fn parse_line(&mut self, spec: &FacilitySpec,
+        type_map: &mut HashMap<String, u32>)
+        -> Result<(usize, Floor), ParseError> {
+
+        self.expect_word("the")?;
+        let floor_idx = self.parse_ordinal()?;
+        self.expect_word("floor")?;
+        self.expect_word("contains")?;
+        let mut floor = self.parse_items(spec, type_map)?;
+
+        if !self.next_is(TokenKind::Period) {
+            return Err(self.error("expected '.'".to_string()));
+        }
+        self.pos += 1;
+        if self.peek().is_some() {
+            return Err(self.error("expected end of line".to_string()));
+        }
+
+        sort_floor_items(&mut floor);
+        Ok((floor_idx, floor))
+    }
+}
+
+// Parses one line of the input into the floor it describes and that floor's
+// items.
+pub fn line_to_floor(line: &str, line_num: usize, spec: &FacilitySpec,
+    type_map: &mut HashMap<String, u32>)
+    -> Result<(usize, Floor), ParseError> {
+
+    FloorParser::new(line, line_num).parse_line(spec, type_map)
+}
+
+// Reads the floors from the input. Floors are placed by their ordinal, not by
+// line order, and floors that aren't described are empty. If the spec didn't
+// set a floor count the facility gets one from the highest floor described.
+pub fn get_init_state(input: &str, mut spec: FacilitySpec,
+    type_map: &mut HashMap<String, u32>) -> Result<State, ParseError> {
+
+    let mut described: Vec<(usize, Floor)> = vec![];
+    // The line describing the highest floor, for reporting a facility that's
+    // too short.
+    let mut top_line: Option<(usize, &str)> = None;
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (floor_idx, floor) = line_to_floor(line, i + 1, &spec, type_map)?;
+        if described.iter().any(|(idx, _)| *idx == floor_idx) {
+            return Err(ParseError::whole_line(INPUT_FILE, i + 1, line,
+                format!("the {} floor is described more than once",
+                    FLOOR_ORDINALS[floor_idx])));
+        }
+        if described.iter().all(|(idx, _)| *idx < floor_idx) {
+            top_line = Some((i + 1, line));
+        }
+        described.push((floor_idx, floor));
+    }
+
+    let num_input_floors = described.iter()
+        .map(|(idx, _)| idx + 1)
+        .max()
+        .unwrap_or(1);
+    if spec.num_floors == 0 {
+        spec.num_floors = num_input_floors;
+    } else if spec.num_floors < num_input_floors {
+        let (line_num, line) = top_line.unwrap();
+        return Err(ParseError::whole_line(INPUT_FILE, line_num, line,
+            format!("{} floors configured but the input describes {}",
+                spec.num_floors, num_input_floors)));
+    }
+
+    let spec = Rc::new(spec);
+    let mut facility = Facility {
+        spec: spec.clone(),
+        elev_floor: 0,
+        floors: vec![Floor::new(); spec.num_floors],
+    };
+    for (floor_idx, floor) in described {
+        facility.floors[floor_idx] = floor;
+    }
+
+    Ok(State {
+        cur_move: 0,
+        facility: facility,
+        parent: None,
+        last_move: None,
+    })
+}
+
+fn add_floor_mutations(floor: &Floor, first_idx: usize, max_items: usize,
+    chosen: &mut Vec<usize>, results: &mut Vec<(Vec<Item>, Floor)>) {
+
+    for i in first_idx..floor.len() {
+        chosen.push(i);
+
+        let to_move = chosen.iter().map(|&idx| floor[idx]).collect();
+        let new_floor = floor.iter().enumerate()
+            .filter(|(idx, _)| !chosen.contains(idx))
+            .map(|(_, item)| *item)
+            .collect();
+        results.push((to_move, new_floor));
+
+        if chosen.len() < max_items {
+            add_floor_mutations(floor, i + 1, max_items, chosen, results);
+        }
+
+        chosen.pop();
+    }
+}
+
+// Every way of loading between one and `max_items` items from the floor into
+// the elevator, paired with what is left behind.
+fn get_floor_mutations(floor: &Floor, max_items: usize)
+    -> Vec<(Vec<Item>, Floor)> {
+
+    let mut results: Vec<(Vec<Item>, Floor)> = vec![];
+    add_floor_mutations(floor, 0, max_items, &mut vec![], &mut results);
+    results
+}
+
+pub fn is_legal_facility(facility: &Facility) -> bool {
+    let kinds = &facility.spec.item_kinds;
+    for floor in facility.floors.iter() {
+        let generators = floor.iter()
+            .filter(|item| kinds[item.kind].radiates)
+            .map(|item| item.element)
+            .collect::<HashSet<u32>>();
+        let chips = floor.iter()
+            .filter(|item| kinds[item.kind].vulnerable)
+            .map(|item| item.element)
+            .collect::<Vec<u32>>();
+
+        for chip_num in chips {
+            if !generators.is_empty() && !generators.contains(&chip_num) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn apply_facility_mutation(
+    facility: &Facility, new_floor_idx: usize, items_to_move: &[Item],
+    new_floor: &Floor)
+    -> Option<Facility> {
+
+    if new_floor_idx >= facility.floors.len() {
+        return None;
+    }
+
+    let mut new_facility = facility.clone();
+    new_facility.elev_floor = new_floor_idx;
+    new_facility.floors[facility.elev_floor] = new_floor.clone();
+    new_facility.floors[new_floor_idx].extend_from_slice(items_to_move);
+
+    sort_floor_items(&mut new_facility.floors[new_floor_idx]);
+
+    if !is_legal_facility(&new_facility) {
+        // println!("not legal facility...");
+        return None;
+    }
+
+    Some(new_facility)
+}
+
+fn generate_next_states(state: &State, state_idx: usize) -> Vec<State> {
+    let mut next_states = vec![];
+
+    let cur_floor = state.facility.elev_floor;
+    let mutations = get_floor_mutations(
+        &state.facility.floors[cur_floor],
+        state.facility.spec.elevator_capacity);
+
+    for (to_move, resulting_floor) in mutations.iter() {
+        // println!("{:?}, {:?}", to_move, resulting_floor);
+
+        let mut dest_floors = vec![];
+        if cur_floor > 0 {
+            dest_floors.push(cur_floor - 1);
+        }
+        if cur_floor < state.facility.floors.len() - 1 {
+            dest_floors.push(cur_floor + 1);
+        }
+
+        for dest_floor in dest_floors {
+            if let Some(new_facility) = apply_facility_mutation(
+                &state.facility, dest_floor, to_move, resulting_floor) {
+
+                next_states.push(
+                    State {
+                        cur_move: state.cur_move + 1,
+                        facility: new_facility,
+                        parent: Some(state_idx),
+                        last_move: Some(Move {
+                            from_floor: cur_floor,
+                            to_floor: dest_floor,
+                            items: to_move.clone(),
+                        }),
+                    });
+            }
+        }
+    }
+
+    next_states
+}
+
+pub fn is_final_state(facility: &Facility) -> bool {
+    facility.floors[0..(facility.floors.len() - 1)].iter()
+        .all(|floor| floor.is_empty())
+}
+
+// Element numbers are interchangeable, so two facilities that differ only by
+// a renaming of elements are equally far from the goal. The key records the
+// (kind, floor) placements of each element's items in sorted order, which is
+// the same for all such renamings.
+fn facility_key(facility: &Facility) -> String {
+    let mut placements: HashMap<u32, Vec<(usize, usize)>> = HashMap::new();
+    for (floor_idx, floor) in facility.floors.iter().enumerate() {
+        for item in floor.iter() {
+            placements.entry(item.element).or_insert(vec![])
+                .push((item.kind, floor_idx));
+        }
+    }
+
+    let mut placements = placements.into_values()
+        .map(|mut element_items| {
+            element_items.sort();
+            element_items
+        })
+        .collect::<Vec<Vec<(usize, usize)>>>();
+    placements.sort();
+
+    format!("{}:{:?}", facility.elev_floor, placements)
+}
+
+// Lower bound on the number of moves left. Every move crosses exactly one
+// floor boundary, so the bound is summed per boundary. All items at or below
+// a boundary have to cross it going up, at most a full elevator at a time,
+// and every trip back down has to bring at least one item with it. If the
+// elevator is above the boundary it also has to come down first.
+fn estimate_remaining_moves(facility: &Facility) -> u32 {
+    let cap = facility.spec.elevator_capacity;
+    let mut estimate = 0;
+    let mut items_below = 0;
+
+    for boundary in 0..(facility.floors.len() - 1) {
+        items_below += facility.floors[boundary].len();
+        if items_below == 0 {
+            continue;
+        }
+
+        let crossings = if cap <= 1 {
+            items_below
+        } else if facility.elev_floor <= boundary {
+            let extra = items_below.saturating_sub(cap);
+            2 * ((extra + cap - 2) / (cap - 1)) + 1
+        } else {
+            2 * ((items_below + cap - 2) / (cap - 1))
+        };
+        estimate += crossings as u32;
+    }
+
+    estimate
+}
+
+// Follows the parent links from a state back to the initial state.
+fn get_path_to(states: &[State], state_idx: usize)
+    -> (Vec<Move>, Vec<Facility>) {
+
+    let mut moves = vec![];
+    let mut facilities = vec![];
+    let mut cur_idx = Some(state_idx);
+    while let Some(idx) = cur_idx {
+        if let Some(ref last_move) = states[idx].last_move {
+            moves.push(last_move.clone());
+        }
+        facilities.push(states[idx].facility.clone());
+        cur_idx = states[idx].parent;
+    }
+
+    moves.reverse();
+    facilities.reverse();
+    (moves, facilities)
+}
+
+pub fn solve(init_state: State) -> Option<Solution> {
+    let mut states = vec![init_state];
+    let mut best_moves = HashMap::<String, u32>::new();
+    let mut open = BinaryHeap::new();
+
+    best_moves.insert(facility_key(&states[0].facility), 0);
+    open.push(OpenEntry {
+        est_total: estimate_remaining_moves(&states[0].facility),
+        cur_move: 0,
+        state_idx: 0,
+    });
+
+    while let Some(entry) = open.pop() {
+        let state_idx = entry.state_idx;
+        let key = facility_key(&states[state_idx].facility);
+        if best_moves[&key] < entry.cur_move {
+            // A shorter route to this state was found after this entry was
+            // queued.
+            continue;
+        }
+
+        if is_final_state(&states[state_idx].facility) {
+            let (moves, facilities) = get_path_to(&states, state_idx);
+            return Some(Solution {
+                num_moves: states[state_idx].cur_move,
+                moves: moves,
+                facilities: facilities,
+            });
+        }
+
+        for next_state in generate_next_states(&states[state_idx], state_idx) {
+            let next_key = facility_key(&next_state.facility);
+            let is_improvement = match best_moves.get(&next_key) {
+                Some(&best) => next_state.cur_move < best,
+                None => true,
+            };
+            if !is_improvement {
+                continue;
+            }
+
+            best_moves.insert(next_key, next_state.cur_move);
+            open.push(OpenEntry {
+                est_total: next_state.cur_move +
+                    estimate_remaining_moves(&next_state.facility),
+                cur_move: next_state.cur_move,
+                state_idx: states.len(),
+            });
+            states.push(next_state);
+        }
+    }
+
+    None
+}
+
+pub fn print_solution(solution: &Solution) {
+    let spec = &solution.facilities[0].spec;
+
+    println!("initial state:");
+    println!("{}", solution.facilities[0]);
+
+    for (i, m) in solution.moves.iter().enumerate() {
+        println!("move {}: {}", i + 1, MoveDisp(spec, m));
+        println!("{}", solution.facilities[i + 1]);
+    }
+
+    println!("found final state in {} moves", solution.num_moves);
+}
+
+// Replays the moves from the starting layout without using anything the
+// solver computed, checking that every trip is one the rules allow and that
+// every layout along the way is legal.
+pub fn validate_solution(init_facility: &Facility, solution: &Solution)
+    -> Result<(), String> {
+
+    if solution.moves.len() != solution.num_moves as usize {
+        return Err(format!("plan has {} moves but claims {}",
+            solution.moves.len(), solution.num_moves));
+    }
+
+    let spec = &init_facility.spec;
+    let mut facility = init_facility.clone();
+    if !is_legal_facility(&facility) {
+        return Err("initial state is not legal".to_string());
+    }
+
+    for (i, m) in solution.moves.iter().enumerate() {
+        let move_num = i + 1;
+        let disp = MoveDisp(spec, m);
+
+        if m.from_floor != facility.elev_floor {
+            return Err(format!("move {} ({}): elevator is on floor {}",
+                move_num, disp, facility.elev_floor + 1));
+        }
+        if m.to_floor >= facility.floors.len() ||
+            (m.to_floor as isize - m.from_floor as isize).abs() != 1 {
+            return Err(format!("move {} ({}): not an adjacent floor",
+                move_num, disp));
+        }
+        if m.items.is_empty() || m.items.len() > spec.elevator_capacity {
+            return Err(format!("move {} ({}): carries {} items",
+                move_num, disp, m.items.len()));
+        }
+
+        for item in m.items.iter() {
+            let from = &mut facility.floors[m.from_floor];
+            match from.iter().position(|other| other == item) {
+                Some(idx) => { from.remove(idx); },
+                None => return Err(format!(
+                    "move {} ({}): {} is not on floor {}",
+                    move_num, disp, ItemDisp(spec, item), m.from_floor + 1)),
+            }
+            facility.floors[m.to_floor].push(*item);
+        }
+        sort_floor_items(&mut facility.floors[m.to_floor]);
+        facility.elev_floor = m.to_floor;
+
+        if !is_legal_facility(&facility) {
+            return Err(format!("move {} ({}): leads to an illegal state\n{}",
+                move_num, disp, facility));
+        }
+    }
+
+    if !is_final_state(&facility) {
+        return Err(format!(
+            "plan ends before everything is on the top floor\n{}", facility));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,254 @@
+// Sets of unsigned integers stored as sorted runs of consecutive values, for
+// when the sets are too big to list value by value. Any unsigned width from
+// u8 to u128 works. Runs are inclusive at both ends so that they can reach
+// the largest value of the type, and a set never holds two runs that overlap
+// or touch, so each gap between runs holds at least one value.
+//
+// Pull it into a day with:
+//
+//     #[path = "../../common/interval_set.rs"]
+//     mod interval_set;
+
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+
+// The unsigned integer types an IntervalSet can hold.
+pub trait Bound: Copy + Ord + Debug {
+    const MIN: Self;
+    const MAX: Self;
+
+    fn succ(self) -> Option<Self>;
+    fn pred(self) -> Option<Self>;
+    fn to_u128(self) -> u128;
+}
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {
+        $(
+            impl Bound for $t {
+                const MIN: $t = 0;
+                const MAX: $t = <$t>::MAX;
+
+                fn succ(self) -> Option<$t> {
+                    self.checked_add(1)
+                }
+
+                fn pred(self) -> Option<$t> {
+                    self.checked_sub(1)
+                }
+
+                fn to_u128(self) -> u128 {
+                    self as u128
+                }
+            }
+        )*
+    }
+}
+
+impl_bound!(u8, u16, u32, u64, u128, usize);
+
+// The values from `lo` to `hi`, both included.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Interval<T> {
+    pub lo: T,
+    pub hi: T,
+}
+
+impl<T: Bound> Interval<T> {
+    pub fn new(lo: T, hi: T) -> Interval<T> {
+        assert!(lo <= hi, "interval {:?}-{:?} is backwards", lo, hi);
+        Interval { lo, hi }
+    }
+
+    // Every value of the type.
+    pub fn full() -> Interval<T> {
+        Interval::new(T::MIN, T::MAX)
+    }
+
+    // Counted in a u128 so that even the full u64 range fits. Only the full
+    // u128 range is too big, which gives None.
+    pub fn len(&self) -> Option<u128> {
+        (self.hi.to_u128() - self.lo.to_u128()).checked_add(1)
+    }
+
+    // Whether `next`, which starts no earlier than this interval, overlaps
+    // it or starts right after it.
+    fn reaches(&self, next: &Interval<T>) -> bool {
+        match self.hi.succ() {
+            Some(after) => next.lo <= after,
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Interval<T>>,
+}
+
+impl<T: Bound> IntervalSet<T> {
+    pub fn new() -> IntervalSet<T> {
+        IntervalSet { ranges: vec![] }
+    }
+
+    // Sorts and merges any number of possibly overlapping intervals in
+    // O(n log n).
+    pub fn from_ranges<I>(ranges: I) -> IntervalSet<T>
+        where I: IntoIterator<Item = Interval<T>> {
+
+        let mut ranges: Vec<Interval<T>> = ranges.into_iter().collect();
+        ranges.sort_by_key(|range| range.lo);
+        IntervalSet::from_sorted(ranges)
+    }
+
+    // Merges intervals that are already sorted by their low ends.
+    fn from_sorted<I>(ranges: I) -> IntervalSet<T>
+        where I: IntoIterator<Item = Interval<T>> {
+
+        let mut merged: Vec<Interval<T>> = vec![];
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if last.reaches(&range) => {
+                    if range.hi > last.hi {
+                        last.hi = range.hi;
+                    }
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        IntervalSet { ranges: merged }
+    }
+
+    pub fn ranges(&self) -> &[Interval<T>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // The number of values in the set, or None if that doesn't fit in a
+    // u128.
+    pub fn len(&self) -> Option<u128> {
+        self.ranges.iter()
+            .try_fold(0u128, |total, range| total.checked_add(range.len()?))
+    }
+
+    pub fn contains(&self, val: T) -> bool {
+        let idx = self.ranges.partition_point(|range| range.hi < val);
+        idx < self.ranges.len() && self.ranges[idx].lo <= val
+    }
+
+    // Adds `range`, merging it with any runs it overlaps or touches.
+    pub fn insert(&mut self, range: Interval<T>) {
+        // The runs from `first` up to `end` are the ones that merge with it.
+        let first = self.ranges
+            .partition_point(|existing| !existing.reaches(&range));
+        let end = self.ranges.partition_point(|existing| {
+            existing.lo <= range.hi
+                || range.hi.succ().is_some_and(|after| existing.lo == after)
+        });
+
+        let mut merged = range;
+        if first < end {
+            merged.lo = std::cmp::min(merged.lo, self.ranges[first].lo);
+            merged.hi = std::cmp::max(merged.hi, self.ranges[end - 1].hi);
+        }
+        self.ranges.splice(first..end, Some(merged));
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut all = Vec::with_capacity(self.ranges.len()
+            + other.ranges.len());
+        let (mut a, mut b) = (0, 0);
+        while a < self.ranges.len() || b < other.ranges.len() {
+            let take_a = b == other.ranges.len() || (a < self.ranges.len()
+                && self.ranges[a].lo <= other.ranges[b].lo);
+            if take_a {
+                all.push(self.ranges[a]);
+                a += 1;
+            } else {
+                all.push(other.ranges[b]);
+                b += 1;
+            }
+        }
+
+        IntervalSet::from_sorted(all)
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut common = vec![];
+        let (mut a, mut b) = (0, 0);
+        while a < self.ranges.len() && b < other.ranges.len() {
+            let range_a = self.ranges[a];
+            let range_b = other.ranges[b];
+            let lo = std::cmp::max(range_a.lo, range_b.lo);
+            let hi = std::cmp::min(range_a.hi, range_b.hi);
+            if lo <= hi {
+                common.push(Interval::new(lo, hi));
+            }
+
+            if range_a.hi < range_b.hi {
+                a += 1;
+            } else {
+                b += 1;
+            }
+        }
+
+        IntervalSet { ranges: common }
+    }
+
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        self.intersection(&other.complement(Interval::full()))
+    }
+
+    // Every value in `universe` that isn't in the set.
+    pub fn complement(&self, universe: Interval<T>) -> IntervalSet<T> {
+        let mut gaps = vec![];
+        let mut next_free = Some(universe.lo);
+
+        for range in &self.ranges {
+            let free = match next_free {
+                Some(free) if free <= universe.hi => free,
+                _ => break,
+            };
+            if range.hi < free {
+                continue;
+            }
+
+            if range.lo > free {
+                let gap_hi = std::cmp::min(range.lo.pred().unwrap(),
+                    universe.hi);
+                gaps.push(Interval::new(free, gap_hi));
+            }
+            next_free = range.hi.succ();
+        }
+
+        if let Some(free) = next_free {
+            if free <= universe.hi {
+                gaps.push(Interval::new(free, universe.hi));
+            }
+        }
+
+        IntervalSet { ranges: gaps }
+    }
+
+    // The smallest value in `universe` that isn't in the set.
+    pub fn lowest_free(&self, universe: Interval<T>) -> Option<T> {
+        self.complement(universe).ranges.first().map(|gap| gap.lo)
+    }
+
+    // How many separate runs of values in `universe` are missing from the
+    // set.
+    pub fn num_gaps(&self, universe: Interval<T>) -> usize {
+        self.complement(universe).ranges.len()
+    }
+
+    // How many values in `universe` are missing from the set, or None if
+    // that doesn't fit in a u128.
+    pub fn num_free(&self, universe: Interval<T>) -> Option<u128> {
+        self.complement(universe).len()
+    }
+}
@@ -0,0 +1,558 @@
+// The office building maze: walls laid out by the designer's formula or any
+// other wall polynomial, a hand-drawn bitmap, or an arbitrary predicate,
+// with searches from a fixed start and ASCII and PPM renders of what the
+// last search explored.
+//
+// It builds on the search and render helpers, so pull all three into a day:
+//
+//     #[path = "../../common/search.rs"]
+//     mod search;
+//     #[path = "../../common/render.rs"]
+//     mod render;
+//     #[path = "../../common/office_maze.rs"]
+//     mod office_maze;
+
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use super::render;
+use super::search;
+
+enum MazeSpace {
+    Wall,
+    Open(u32),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Coord {
+    pub x: u64,
+    pub y: u64,
+}
+
+const CELL_PIXELS: usize = 8;
+
+pub const OFFSETS: [(i64, i64); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+// One term of a wall polynomial: coeff * x^x_pow * y^y_pow.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Term {
+    coeff: u64,
+    x_pow: u32,
+    y_pow: u32,
+}
+
+// A position is a wall when the polynomial's value has an odd number of set
+// bits, as with the office designer's formula. Values are computed in 128
+// bits and a value that doesn't fit is an error rather than a wrapped result.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Polynomial {
+    terms: Vec<Term>,
+}
+
+impl Polynomial {
+    // x*x + 3*x + 2*x*y + y + y*y + favorite
+    pub fn office(favorite: u64) -> Polynomial {
+        let term = |coeff, x_pow, y_pow| Term {
+            coeff: coeff,
+            x_pow: x_pow,
+            y_pow: y_pow,
+        };
+
+        Polynomial {
+            terms: vec![
+                term(1, 2, 0),
+                term(3, 1, 0),
+                term(2, 1, 1),
+                term(1, 0, 1),
+                term(1, 0, 2),
+                term(favorite, 0, 0),
+            ],
+        }
+    }
+
+    // Parses a sum of terms such as "x^2 + 3x + 2xy + y + y^2 + 1362". Each
+    // term is an optional coefficient followed by optional x and y factors,
+    // each with an optional ^power.
+    pub fn parse(s: &str) -> Result<Polynomial, String> {
+        fn parse_num<T: FromStr>(digits: &str, term: &str)
+            -> Result<T, String> {
+
+            T::from_str(digits).map_err(|_| {
+                format!("bad number '{}' in term '{}'", digits, term)
+            })
+        }
+
+        let mut terms = vec![];
+        for term_str in s.split('+').map(|t| t.trim()) {
+            let mut rest = term_str;
+            let mut term = Term { coeff: 1, x_pow: 0, y_pow: 0 };
+
+            let digits_len = rest.find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if digits_len > 0 {
+                term.coeff = parse_num(&rest[..digits_len], term_str)?;
+                rest = rest[digits_len..].trim_start_matches('*');
+            }
+
+            for (var, pow) in [('x', &mut term.x_pow), ('y', &mut term.y_pow)]
+                .iter_mut() {
+
+                if !rest.starts_with(*var) {
+                    continue;
+                }
+                rest = rest[1..].trim_start_matches('*');
+                **pow = 1;
+
+                if rest.starts_with('^') {
+                    let digits_len = rest[1..]
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(rest.len() - 1);
+                    **pow = parse_num(&rest[1..(digits_len + 1)], term_str)?;
+                    rest = rest[(digits_len + 1)..].trim_start_matches('*');
+                }
+            }
+
+            if term_str.is_empty() || !rest.is_empty() {
+                return Err(format!("bad polynomial term '{}'", term_str));
+            }
+            terms.push(term);
+        }
+
+        Ok(Polynomial { terms: terms })
+    }
+
+    // None if the value doesn't fit in 128 bits.
+    pub fn eval(&self, pos: Coord) -> Option<u128> {
+        self.terms.iter()
+            .try_fold(0u128, |sum, term| {
+                let x = (pos.x as u128).checked_pow(term.x_pow)?;
+                let y = (pos.y as u128).checked_pow(term.y_pow)?;
+                let val = (term.coeff as u128).checked_mul(x)?.checked_mul(y)?;
+                sum.checked_add(val)
+            })
+    }
+
+    pub fn is_wall(&self, pos: Coord) -> Result<bool, String> {
+        let mut val = match self.eval(pos) {
+            Some(val) => val,
+            None => return Err(format!(
+                "wall polynomial overflows 128 bits at {},{}", pos.x, pos.y)),
+        };
+
+        let mut num_ones = 0;
+        while val != 0 {
+            if val & 1 == 1 {
+                num_ones += 1;
+            }
+
+            val >>= 1;
+        }
+
+        Ok((num_ones & 1) == 1)
+    }
+}
+
+// Hand-drawn maze: one row per line, '#' for a wall and '.' for open space.
+// Everything outside the drawing counts as wall.
+pub fn parse_bitmap(s: &str) -> Result<Vec<Vec<bool>>, String> {
+    s.lines()
+        .enumerate()
+        .map(|(y, line)| {
+            line.trim_end().chars()
+                .enumerate()
+                .map(|(x, c)| match c {
+                    '#' => Ok(true),
+                    '.' => Ok(false),
+                    _ => Err(format!("bad bitmap char '{}' at {},{}", c, x, y)),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+type WallFn = Box<dyn Fn(Coord) -> Result<bool, String>>;
+
+// Answer to a reachability query. `Unreachable` is only given with proof:
+// the target is a wall, or the open region around the start or the target
+// was explored to the end without finding the other. `Unknown` means both
+// regions are larger than the search was allowed to explore.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reach {
+    Dist(u32),
+    Unreachable,
+    Unknown,
+}
+
+// `spaces` holds what the last search explored: the distance to every open
+// space it reached and the walls bordering them. Any predicate can decide
+// where the walls are. The first error the predicate gives during a search
+// is held in `wall_error` until the query reports it.
+pub struct Maze {
+    start_coord: Coord,
+    spaces: HashMap<Coord, MazeSpace>,
+    wall_fn: WallFn,
+    wall_error: RefCell<Option<String>>,
+}
+
+impl search::Neighbors for Maze {
+    type Node = Coord;
+
+    fn neighbors(&self, pos: &Coord) -> Vec<Coord> {
+        OFFSETS.iter()
+            .filter_map(|&(x_offset, y_offset)| {
+                Maze::offset_pos(*pos, x_offset, y_offset)
+            })
+            .filter(|new_pos| !self.is_wall(*new_pos))
+            .collect()
+    }
+}
+
+impl Maze {
+    // The office building, laid out by the designer's favorite number.
+    pub fn new(start_coord: Coord, wall_param: u64) -> Maze {
+        Maze::from_polynomial(start_coord, Polynomial::office(wall_param))
+    }
+
+    pub fn with_wall_fn<F>(start_coord: Coord, wall_fn: F) -> Maze
+        where F: Fn(Coord) -> bool + 'static {
+
+        Maze::with_fallible_wall_fn(start_coord, move |pos| Ok(wall_fn(pos)))
+    }
+
+    pub fn with_fallible_wall_fn<F>(start_coord: Coord, wall_fn: F) -> Maze
+        where F: Fn(Coord) -> Result<bool, String> + 'static {
+
+        let mut new_maze = Maze {
+            start_coord: start_coord,
+            spaces: HashMap::new(),
+            wall_fn: Box::new(wall_fn),
+            wall_error: RefCell::new(None),
+        };
+        new_maze.spaces.insert(
+            start_coord,
+            MazeSpace::Open(0));
+        new_maze
+    }
+
+    pub fn from_polynomial(start_coord: Coord, poly: Polynomial) -> Maze {
+        Maze::with_fallible_wall_fn(start_coord, move |pos| poly.is_wall(pos))
+    }
+
+    pub fn from_bitmap(start_coord: Coord, bitmap: Vec<Vec<bool>>) -> Maze {
+        Maze::with_wall_fn(start_coord, move |pos| {
+            bitmap.get(pos.y as usize)
+                .and_then(|row| row.get(pos.x as usize))
+                .cloned()
+                .unwrap_or(true)
+        })
+    }
+
+    // None if the offset leaves the grid at either end.
+    pub fn offset_pos(pos: Coord, x_offset: i64, y_offset: i64)
+        -> Option<Coord> {
+
+        Some(
+            Coord {
+                x: pos.x.checked_add_signed(x_offset)?,
+                y: pos.y.checked_add_signed(y_offset)?,
+            })
+    }
+
+    // A space the wall predicate fails on is treated as a wall so the
+    // search can carry on. The query then reports the failure.
+    fn is_wall(&self, pos: Coord) -> bool {
+        match (self.wall_fn)(pos) {
+            Ok(is_wall) => is_wall,
+            Err(err) => {
+                let mut wall_error = self.wall_error.borrow_mut();
+                if wall_error.is_none() {
+                    *wall_error = Some(err);
+                }
+                true
+            },
+        }
+    }
+
+    fn take_wall_error(&self) -> Result<(), String> {
+        match self.wall_error.borrow_mut().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn record_explored(&mut self, result: &search::SearchResult<Coord>) {
+        self.spaces.clear();
+        for (pos, dist) in result.dists.iter() {
+            self.spaces.insert(*pos, MazeSpace::Open(*dist));
+        }
+
+        for pos in result.dists.keys() {
+            for &(x_offset, y_offset) in OFFSETS.iter() {
+                let new_pos = Maze::offset_pos(*pos, x_offset, y_offset);
+                if let Some(new_pos) = new_pos {
+                    if self.is_wall(new_pos) {
+                        self.spaces.insert(new_pos, MazeSpace::Wall);
+                    }
+                }
+            }
+        }
+    }
+
+    // Shortest route from the start to `end_coord`, both ends included. Like
+    // `dists_to_all`, the search gives up once `max_cells` cells have been
+    // reached, then looks for the start from the target's side, so an
+    // unreachable target in an infinite maze still comes back.
+    pub fn route_to(&mut self, end_coord: Coord, max_cells: usize)
+        -> Result<Vec<Coord>, String> {
+
+        let end_is_wall = self.is_wall(end_coord);
+        self.take_wall_error()?;
+        if end_is_wall {
+            return Err("no solution: wall".to_string());
+        }
+
+        let result = search::bfs_bounded(self, self.start_coord,
+            |pos| *pos == end_coord, max_cells);
+        self.record_explored(&result);
+        self.take_wall_error()?;
+
+        if let Some(route) = result.goal_path() {
+            return Ok(route);
+        }
+        if result.complete {
+            return Err("no solution: unreachable".to_string());
+        }
+
+        let start_coord = self.start_coord;
+        let back = search::bfs_bounded(self, end_coord,
+            |pos| *pos == start_coord, max_cells);
+        self.take_wall_error()?;
+
+        match back.goal_path() {
+            Some(mut route) => {
+                route.reverse();
+                Ok(route)
+            }
+            None if back.complete =>
+                Err("no solution: unreachable".to_string()),
+            None => Err(format!(
+                "no solution: unknown, gave up after {} cells", max_cells)),
+        }
+    }
+
+    pub fn dist_to(&mut self, end_coord: Coord, max_cells: usize)
+        -> Result<u32, String> {
+
+        Ok(self.route_to(end_coord, max_cells)?.len() as u32 - 1)
+    }
+
+    // Distances to several targets from a single expansion out of the
+    // start, which stops once every target is found or `max_cells` cells
+    // have been reached. A target the expansion didn't settle is then
+    // searched for from its own side, which proves it unreachable if it sits
+    // in an enclosed region.
+    pub fn dists_to_all(&mut self, targets: &[Coord], max_cells: usize)
+        -> Result<Vec<Reach>, String> {
+
+        let mut remaining = targets.iter()
+            .filter(|target| !self.is_wall(**target))
+            .cloned()
+            .collect::<HashSet<Coord>>();
+
+        let result = search::bfs_bounded(self, self.start_coord, |pos| {
+            remaining.remove(pos);
+            remaining.is_empty()
+        }, max_cells);
+        self.record_explored(&result);
+
+        let reaches = targets.iter()
+            .map(|target| {
+                if self.is_wall(*target) {
+                    Reach::Unreachable
+                } else if let Some(dist) = result.dist_to(target) {
+                    Reach::Dist(dist)
+                } else if result.complete {
+                    Reach::Unreachable
+                } else {
+                    self.search_from_target(*target, max_cells)
+                }
+            })
+            .collect();
+
+        self.take_wall_error()?;
+        Ok(reaches)
+    }
+
+    pub fn is_reachable(&mut self, target: Coord, max_cells: usize)
+        -> Result<Reach, String> {
+
+        Ok(self.dists_to_all(&[target], max_cells)?[0])
+    }
+
+    // Moves are reversible, so searching from the target for the start
+    // finds the same distance.
+    fn search_from_target(&self, target: Coord, max_cells: usize) -> Reach {
+        let start_coord = self.start_coord;
+        let result = search::bfs_bounded(self, target,
+            |pos| *pos == start_coord, max_cells);
+
+        match result.goal_dist() {
+            Some(dist) => Reach::Dist(dist),
+            None if result.complete => Reach::Unreachable,
+            None => Reach::Unknown,
+        }
+    }
+
+    pub fn fill_to_dist(&mut self, max_dist: u32) -> Result<(), String> {
+        let result = search::flood_fill(self, self.start_coord,
+            Some(max_dist));
+        self.record_explored(&result);
+        self.take_wall_error()
+    }
+
+    // Every open space at most `max_dist` steps from the start.
+    pub fn cells_within(&mut self, max_dist: u32)
+        -> Result<HashSet<Coord>, String> {
+
+        let result = search::flood_fill(self, self.start_coord,
+            Some(max_dist));
+        self.record_explored(&result);
+        self.take_wall_error()?;
+
+        Ok(result.dists.keys().cloned().collect())
+    }
+
+    // The whole open region around the start, or None if it has more than
+    // `max_cells` spaces and so may go on forever.
+    pub fn reachable_region(&mut self, max_cells: usize)
+        -> Result<Option<HashSet<Coord>>, String> {
+
+        let result = search::bfs_bounded(self, self.start_coord,
+            |_| false, max_cells);
+        self.record_explored(&result);
+        self.take_wall_error()?;
+
+        if result.complete {
+            Ok(Some(result.dists.keys().cloned().collect()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // The open space farthest from the start and its distance, or None if
+    // the region around the start has more than `max_cells` spaces. Ties go
+    // to the space nearest the top, then the left.
+    pub fn farthest_reachable(&mut self, max_cells: usize)
+        -> Result<Option<(Coord, u32)>, String> {
+
+        let result = search::bfs_bounded(self, self.start_coord,
+            |_| false, max_cells);
+        self.record_explored(&result);
+        self.take_wall_error()?;
+
+        if !result.complete {
+            return Ok(None);
+        }
+
+        Ok(result.dists.iter()
+            .map(|(pos, dist)| (*pos, *dist))
+            .max_by(|(a_pos, a_dist), (b_pos, b_dist)| {
+                a_dist.cmp(b_dist)
+                    .then_with(|| (b_pos.y, b_pos.x).cmp(&(a_pos.y, a_pos.x)))
+            }))
+    }
+
+    // Largest x and y of anything explored, so renders start at (0, 0) and
+    // cover everything the last search saw.
+    fn explored_bounds(&self) -> (u64, u64) {
+        self.spaces.keys()
+            .fold((0, 0), |(max_x, max_y), pos| {
+                (std::cmp::max(max_x, pos.x), std::cmp::max(max_y, pos.y))
+            })
+    }
+
+    // '#' for walls, '.' for open spaces, 'O' for spaces on `route` and ' '
+    // for anything the last search didn't look at.
+    pub fn render_ascii(&self, route: &[Coord]) -> String {
+        let (max_x, max_y) = self.explored_bounds();
+        let route = route.iter().collect::<HashSet<&Coord>>();
+
+        let mut s = String::new();
+        for y in 0..=max_y {
+            for x in 0..=max_x {
+                let pos = Coord { x: x, y: y };
+                let c = match self.spaces.get(&pos) {
+                    _ if route.contains(&pos) => 'O',
+                    Some(MazeSpace::Wall) => '#',
+                    Some(MazeSpace::Open(_)) => '.',
+                    None => ' ',
+                };
+                s.push(c);
+            }
+            s.push('\n');
+        }
+
+        s
+    }
+
+    // Binary PPM (P6) image with each space drawn as a CELL_PIXELS square.
+    // Open spaces are shaded from blue (near the start) to red (farthest
+    // away), the route is white, walls are grey and unexplored space is black.
+    pub fn render_ppm(&self, route: &[Coord]) -> Vec<u8> {
+        let (max_x, max_y) = self.explored_bounds();
+        let route = route.iter().collect::<HashSet<&Coord>>();
+        let max_dist = self.spaces.values()
+            .filter_map(|space| match space {
+                MazeSpace::Open(dist) => Some(*dist),
+                MazeSpace::Wall => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let cols = max_x as usize + 1;
+        let rows = max_y as usize + 1;
+
+        render::render_ppm(cols, rows, CELL_PIXELS, |x, y| {
+            let pos = Coord { x: x as u64, y: y as u64 };
+            match self.spaces.get(&pos) {
+                _ if route.contains(&pos) => [255, 255, 255],
+                Some(MazeSpace::Wall) => [96, 96, 96],
+                Some(MazeSpace::Open(dist)) =>
+                    render::heat_color(*dist as u64, max_dist as u64),
+                None => [0, 0, 0],
+            }
+        })
+    }
+
+    pub fn num_marked_reachable(&self, max_dist: u32) -> u32 {
+        let mut num_spaces = 0;
+        for space in self.spaces.values() {
+            match *space {
+                MazeSpace::Open(dist) if dist <= max_dist => { num_spaces += 1},
+                _ => {},
+            }
+        }
+        num_spaces
+    }
+}
+
+// The input is the designer's favorite number, "poly: " followed by a wall
+// polynomial, or a bitmap of the maze.
+pub fn parse_maze(input: &str, start_coord: Coord) -> Result<Maze, String> {
+    let input = input.trim();
+
+    if let Ok(favorite) = u64::from_str(input) {
+        return Ok(Maze::new(start_coord, favorite));
+    }
+
+    if let Some(poly) = input.strip_prefix("poly:") {
+        let poly = Polynomial::parse(poly)?;
+        return Ok(Maze::from_polynomial(start_coord, poly));
+    }
+
+    Ok(Maze::from_bitmap(start_coord, parse_bitmap(input)?))
+}
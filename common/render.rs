@@ -0,0 +1,47 @@
+// Helpers for drawing puzzle grids as pictures: a color ramp for shading
+// cells by a value, and a binary PPM writer for grids of colored cells.
+//
+// Pull it into a day with:
+//
+//     #[path = "../../common/render.rs"]
+//     mod render;
+
+#![allow(dead_code)]
+
+// Shades `val` from blue at 0 through green to red at `max_val` and above.
+pub fn heat_color(val: u64, max_val: u64) -> [u8; 3] {
+    let t = if max_val == 0 { 0.0 } else { val as f32 / max_val as f32 };
+    let t = t.min(1.0);
+    let red = 255.0 * t;
+    let green = 255.0 * (1.0 - (2.0 * t - 1.0).abs());
+    let blue = 255.0 * (1.0 - t);
+
+    [red as u8, green as u8, blue as u8]
+}
+
+// Binary PPM (P6) image of a `cols` by `rows` grid with each cell drawn as a
+// `cell_pixels` square in the color `cell_color(x, y)` gives it.
+pub fn render_ppm<F>(cols: usize, rows: usize, cell_pixels: usize,
+    mut cell_color: F) -> Vec<u8>
+    where F: FnMut(usize, usize) -> [u8; 3] {
+
+    let width = cols * cell_pixels;
+    let height = rows * cell_pixels;
+
+    let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    image.reserve(width * height * 3);
+    for y in 0..rows {
+        let row_colors = (0..cols)
+            .map(|x| cell_color(x, y))
+            .collect::<Vec<[u8; 3]>>();
+        for _ in 0..cell_pixels {
+            for color in &row_colors {
+                for _ in 0..cell_pixels {
+                    image.extend_from_slice(color);
+                }
+            }
+        }
+    }
+
+    image
+}
@@ -0,0 +1,220 @@
+// Graph searches shared by the maze-style days. A day describes its maze by
+// implementing `Neighbors` and then picks whichever search fits:
+//
+//     bfs         fewest steps to a goal, ignoring step costs
+//     flood_fill  every node reachable from the start, optionally capped at a
+//                 maximum number of steps
+//     bfs_bounded bfs that gives up after reaching a given number of nodes
+//     dijkstra    cheapest route to a goal using `step_cost`
+//     astar       dijkstra guided by an admissible heuristic
+//
+// Pull it into a day with:
+//
+//     #[path = "../../common/search.rs"]
+//     mod search;
+
+#![allow(dead_code)]
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+pub trait Neighbors {
+    type Node: Clone + Eq + Hash;
+
+    // Every node that can be reached from `node` in one step.
+    fn neighbors(&self, node: &Self::Node) -> Vec<Self::Node>;
+
+    // Cost of the step from `from` to its neighbor `to`. Only dijkstra and
+    // astar look at this; bfs and flood_fill count every step as 1.
+    fn step_cost(&self, _from: &Self::Node, _to: &Self::Node) -> u32 {
+        1
+    }
+}
+
+// Everything a search learned. `dists` holds the best known distance to each
+// node that was reached and `goal` is the first goal node found, if any.
+// For dijkstra and astar the distances of nodes still waiting in the queue
+// when the goal was found are upper bounds rather than exact.
+//
+// `complete` is set when the search stopped because there was nothing left
+// to expand, not because it found a goal or ran out of budget. A complete
+// search without a distance limit has seen the whole reachable region, so
+// anything missing from `dists` is unreachable.
+pub struct SearchResult<N> {
+    pub dists: HashMap<N, u32>,
+    pub goal: Option<N>,
+    pub complete: bool,
+    parents: HashMap<N, N>,
+}
+
+impl<N: Clone + Eq + Hash> SearchResult<N> {
+    fn new(start: &N) -> SearchResult<N> {
+        let mut dists = HashMap::new();
+        dists.insert(start.clone(), 0);
+
+        SearchResult {
+            dists: dists,
+            goal: None,
+            complete: false,
+            parents: HashMap::new(),
+        }
+    }
+
+    pub fn dist_to(&self, node: &N) -> Option<u32> {
+        self.dists.get(node).cloned()
+    }
+
+    // The route from the start to `node`, both ends included.
+    pub fn path_to(&self, node: &N) -> Option<Vec<N>> {
+        if !self.dists.contains_key(node) {
+            return None;
+        }
+
+        let mut path = vec![node.clone()];
+        let mut cur = node;
+        while let Some(parent) = self.parents.get(cur) {
+            path.push(parent.clone());
+            cur = parent;
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn goal_dist(&self) -> Option<u32> {
+        self.goal.as_ref().and_then(|goal| self.dist_to(goal))
+    }
+
+    pub fn goal_path(&self) -> Option<Vec<N>> {
+        self.goal.as_ref().and_then(|goal| self.path_to(goal))
+    }
+
+    fn record(&mut self, node: &N, parent: &N, dist: u32) {
+        self.dists.insert(node.clone(), dist);
+        self.parents.insert(node.clone(), parent.clone());
+    }
+}
+
+fn breadth_first<G, F>(graph: &G, start: G::Node, mut is_goal: F,
+    max_dist: Option<u32>, max_nodes: Option<usize>) -> SearchResult<G::Node>
+    where G: Neighbors, F: FnMut(&G::Node) -> bool {
+
+    let mut result = SearchResult::new(&start);
+    let mut queue = VecDeque::new();
+    queue.push_back((start, 0));
+
+    while let Some((node, dist)) = queue.pop_front() {
+        if is_goal(&node) {
+            result.goal = Some(node);
+            return result;
+        }
+
+        if let Some(max_dist) = max_dist {
+            if dist >= max_dist {
+                continue;
+            }
+        }
+
+        for next in graph.neighbors(&node) {
+            if result.dists.contains_key(&next) {
+                continue;
+            }
+
+            if let Some(max_nodes) = max_nodes {
+                if result.dists.len() >= max_nodes {
+                    return result;
+                }
+            }
+
+            result.record(&next, &node, dist + 1);
+            queue.push_back((next, dist + 1));
+        }
+    }
+
+    result.complete = true;
+    result
+}
+
+// `is_goal` is called once per node, in order of distance from the start.
+pub fn bfs<G, F>(graph: &G, start: G::Node, is_goal: F)
+    -> SearchResult<G::Node>
+    where G: Neighbors, F: FnMut(&G::Node) -> bool {
+
+    breadth_first(graph, start, is_goal, None, None)
+}
+
+// Stops without a goal once it reaches a node beyond the first `max_nodes`,
+// so it returns even when the graph is infinite. A region of exactly
+// `max_nodes` nodes is still explored to the end.
+pub fn bfs_bounded<G, F>(graph: &G, start: G::Node, is_goal: F,
+    max_nodes: usize) -> SearchResult<G::Node>
+    where G: Neighbors, F: FnMut(&G::Node) -> bool {
+
+    breadth_first(graph, start, is_goal, None, Some(max_nodes))
+}
+
+// Explores everything reachable from `start`, stopping `max_dist` steps out
+// if a limit is given. Without a limit this only returns if the reachable
+// region is finite.
+pub fn flood_fill<G>(graph: &G, start: G::Node, max_dist: Option<u32>)
+    -> SearchResult<G::Node>
+    where G: Neighbors {
+
+    breadth_first(graph, start, |_| false, max_dist, None)
+}
+
+pub fn dijkstra<G, F>(graph: &G, start: G::Node, is_goal: F)
+    -> SearchResult<G::Node>
+    where G: Neighbors, F: Fn(&G::Node) -> bool {
+
+    astar(graph, start, is_goal, |_| 0)
+}
+
+// `heuristic` must never overestimate the remaining cost to a goal, or the
+// route found may not be the cheapest.
+pub fn astar<G, F, H>(graph: &G, start: G::Node, is_goal: F, heuristic: H)
+    -> SearchResult<G::Node>
+    where G: Neighbors, F: Fn(&G::Node) -> bool, H: Fn(&G::Node) -> u32 {
+
+    let mut result = SearchResult::new(&start);
+
+    // The heap holds indexes into `nodes` so that nodes don't need to be Ord.
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((heuristic(&start), 0, 0)));
+    let mut nodes = vec![start];
+
+    while let Some(Reverse((_, dist, node_idx))) = open.pop() {
+        let node = nodes[node_idx].clone();
+        if result.dists[&node] < dist {
+            // A cheaper route here was queued after this one.
+            continue;
+        }
+
+        if is_goal(&node) {
+            result.goal = Some(node);
+            return result;
+        }
+
+        for next in graph.neighbors(&node) {
+            let next_dist = dist + graph.step_cost(&node, &next);
+            let is_improvement = match result.dists.get(&next) {
+                Some(&best) => next_dist < best,
+                None => true,
+            };
+            if !is_improvement {
+                continue;
+            }
+
+            result.record(&next, &node, next_dist);
+            open.push(Reverse((next_dist + heuristic(&next), next_dist,
+                nodes.len())));
+            nodes.push(next);
+        }
+    }
+
+    result.complete = true;
+    result
+}
@@ -0,0 +1,337 @@
+// Closed forms for the elf white elephant party. Elves 1 to n sit in a
+// circle and, starting with elf 1, each elf still holding presents takes all
+// of the presents from another elf, who then leaves the circle. Which elf
+// ends up with everything depends on who gets robbed:
+//
+//     steal_from_left_winner    the next elf to the left, which is the
+//                               Josephus problem with every second elf out
+//     steal_from_across_winner  the elf directly across the circle, rounding
+//                               left when two are equally far
+//
+// Both take O(log n) time and return None for an empty circle. For any other
+// rule, implement `StealRule` and `play` the party out in O(n log n), which
+// also records every theft along the way. `play_traced` hands each theft to
+// a callback as it happens, and `write_trace` uses that to write them out as
+// CSV or JSON Lines. Pull it into a day with:
+//
+//     #[path = "../../common/white_elephant.rs"]
+//     mod white_elephant;
+
+#![allow(dead_code)]
+
+use std::io;
+use std::io::Write;
+
+// Writing n as 2^m + l with l < 2^m, the first l removals leave 2^m elves
+// with elf 2l + 1 about to steal, and from there whoever starts a circle of
+// 2^m elves wins it.
+pub fn steal_from_left_winner(num_elves: u64) -> Option<u64> {
+    if num_elves == 0 {
+        return None;
+    }
+
+    let highest_pow = 1 << (63 - num_elves.leading_zeros());
+    let extra = num_elves - highest_pow;
+
+    Some(2 * extra + 1)
+}
+
+// With p the largest power of 3 no bigger than n, elf n wins when n == p.
+// Otherwise the winner counts up by one for each elf past p until n reaches
+// 2p, and then by two.
+pub fn steal_from_across_winner(num_elves: u64) -> Option<u64> {
+    if num_elves == 0 {
+        return None;
+    }
+
+    let mut pow = 1u64;
+    while let Some(next_pow) = pow.checked_mul(3) {
+        if next_pow > num_elves {
+            break;
+        }
+        pow = next_pow;
+    }
+
+    let winner = if num_elves == pow {
+        num_elves
+    } else if num_elves - pow <= pow {
+        num_elves - pow
+    } else {
+        // 2n - 3p, arranged so that it can't overflow.
+        (num_elves - pow) + (num_elves - 2 * pow)
+    };
+
+    Some(winner)
+}
+
+// Picks who gets robbed. Elves are indexed by their place among those still
+// in the circle, from 0 at the lowest numbered one, and `victim` must return
+// an index other than `cur_idx` that is less than `circle_size`.
+pub trait StealRule {
+    fn victim(&self, cur_idx: usize, circle_size: usize) -> usize;
+}
+
+pub struct StealFromLeft;
+
+impl StealRule for StealFromLeft {
+    fn victim(&self, cur_idx: usize, circle_size: usize) -> usize {
+        (cur_idx + 1) % circle_size
+    }
+}
+
+pub struct StealFromAcross;
+
+impl StealRule for StealFromAcross {
+    fn victim(&self, cur_idx: usize, circle_size: usize) -> usize {
+        (cur_idx + circle_size / 2) % circle_size
+    }
+}
+
+// One theft, with the number of presents that changed hands. A new round
+// starts each time the turn passes the lowest numbered elf, and rounds count
+// from 1.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Steal {
+    pub round: u64,
+    pub thief: u64,
+    pub victim: u64,
+    pub num_presents: u64,
+}
+
+pub struct Party {
+    pub winner: u64,
+    // Every theft in order, so the victims are also the order elves left in.
+    pub steals: Vec<Steal>,
+    // presents[id - 1] is how many presents elf `id` held when it left the
+    // circle, or at the end for the winner.
+    pub presents: Vec<u64>,
+}
+
+// Counts the elves still in the circle over ranges of seats, so that both
+// finding the elf at an index and removing an elf take O(log n).
+struct Circle {
+    tree: Vec<usize>,
+}
+
+impl Circle {
+    fn new(num_elves: usize) -> Circle {
+        let mut tree = vec![0; num_elves + 1];
+        for seat in 1..=num_elves {
+            tree[seat] += 1;
+            let parent = seat + (seat & seat.wrapping_neg());
+            if parent <= num_elves {
+                tree[parent] += tree[seat];
+            }
+        }
+
+        Circle { tree: tree }
+    }
+
+    fn remove(&mut self, seat: usize) {
+        let mut node = seat;
+        while node < self.tree.len() {
+            self.tree[node] -= 1;
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    // The seat, from 1, of the elf at index `idx` among those left.
+    fn seat_at(&self, idx: usize) -> usize {
+        let mut seat = 0;
+        let mut remaining = idx + 1;
+        let mut step = (self.tree.len() - 1).next_power_of_two();
+        while step > 0 {
+            let next = seat + step;
+            if next < self.tree.len() && self.tree[next] < remaining {
+                seat = next;
+                remaining -= self.tree[next];
+            }
+            step /= 2;
+        }
+
+        seat + 1
+    }
+}
+
+// Plays out a party of `num_elves` elves, each starting with one present.
+pub fn play<R: StealRule>(num_elves: usize, rule: &R) -> Option<Party> {
+    play_traced(num_elves, rule, |_| true)
+}
+
+// Like `play`, but calls `on_steal` with each theft as it happens. Stops and
+// returns None as soon as `on_steal` returns false.
+pub fn play_traced<R, F>(num_elves: usize, rule: &R, mut on_steal: F)
+    -> Option<Party>
+    where R: StealRule, F: FnMut(&Steal) -> bool {
+
+    if num_elves == 0 {
+        return None;
+    }
+
+    let mut circle = Circle::new(num_elves);
+    let mut presents = vec![1u64; num_elves];
+    let mut steals = Vec::with_capacity(num_elves - 1);
+
+    let mut cur_idx = 0;
+    let mut round = 1;
+    let mut last_thief = 0;
+    for circle_size in (2..=num_elves).rev() {
+        let victim_idx = rule.victim(cur_idx, circle_size);
+        assert!(victim_idx < circle_size && victim_idx != cur_idx,
+            "elf {} of {} can't rob elf {}", cur_idx, circle_size, victim_idx);
+
+        let thief = circle.seat_at(cur_idx);
+        if thief < last_thief {
+            round += 1;
+        }
+        last_thief = thief;
+
+        let victim = circle.seat_at(victim_idx);
+        let num_presents = presents[victim - 1];
+        let steal = Steal {
+            round: round,
+            thief: thief as u64,
+            victim: victim as u64,
+            num_presents: num_presents,
+        };
+        if !on_steal(&steal) {
+            return None;
+        }
+
+        presents[thief - 1] += num_presents;
+        steals.push(steal);
+        circle.remove(victim);
+
+        // Removing an elf below the thief moves the thief down one index,
+        // which leaves the next elf at the thief's old index.
+        if victim_idx > cur_idx {
+            cur_idx += 1;
+        }
+        cur_idx %= circle_size - 1;
+    }
+
+    Some(Party {
+        winner: circle.seat_at(0) as u64,
+        steals: steals,
+        presents: presents,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TraceFormat {
+    Csv,
+    JsonLines,
+}
+
+pub const CSV_HEADER: &str = "round,thief,victim,num_presents";
+
+impl Steal {
+    pub fn format(&self, format: TraceFormat) -> String {
+        match format {
+            TraceFormat::Csv => format!("{},{},{},{}", self.round, self.thief,
+                self.victim, self.num_presents),
+            TraceFormat::JsonLines => format!(concat!(
+                "{{\"round\":{},\"thief\":{},",
+                "\"victim\":{},\"num_presents\":{}}}"),
+                self.round, self.thief, self.victim, self.num_presents),
+        }
+    }
+}
+
+// Where a trace stops early, if anywhere. With both set it stops at
+// whichever comes first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TraceLimit {
+    pub max_events: Option<usize>,
+    // Stop right after this elf is robbed.
+    pub until_robbed: Option<u64>,
+}
+
+// Plays a party under `rule`, writing each theft to `out` as it happens.
+// Returns how many were written.
+pub fn write_trace<R, W>(num_elves: usize, rule: &R, format: TraceFormat,
+    limit: TraceLimit, out: &mut W) -> io::Result<usize>
+    where R: StealRule, W: Write {
+
+    if format == TraceFormat::Csv {
+        writeln!(out, "{}", CSV_HEADER)?;
+    }
+
+    let mut num_written = 0;
+    let mut write_err = None;
+    play_traced(num_elves, rule, |steal| {
+        if limit.max_events.is_some_and(|max| num_written >= max) {
+            return false;
+        }
+        if let Err(err) = writeln!(out, "{}", steal.format(format)) {
+            write_err = Some(err);
+            return false;
+        }
+        num_written += 1;
+
+        limit.until_robbed != Some(steal.victim)
+    });
+
+    match write_err {
+        Some(err) => Err(err),
+        None => Ok(num_written),
+    }
+}
+
+// Where to write a trace and how much of it, as asked for on the command
+// line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TraceArgs {
+    pub file: String,
+    pub format: TraceFormat,
+    pub limit: TraceLimit,
+}
+
+// Reads the trace options out of the command line arguments after the
+// program name:
+//
+//     --trace <file>          write a trace, as JSON Lines if the file name
+//                             ends in .jsonl and as CSV otherwise
+//     --max-events <n>        stop after n thefts
+//     --until-robbed <elf>    stop once this elf has been robbed
+//
+// Returns None when there's no --trace.
+pub fn parse_trace_args(args: &[String]) -> Result<Option<TraceArgs>, String> {
+    let mut file = None;
+    let mut limit = TraceLimit::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| {
+            args.next().ok_or(format!("{} needs {}", arg, what))
+        };
+
+        match arg.as_str() {
+            "--trace" => file = Some(value("a file name")?.clone()),
+            "--max-events" => {
+                let value = value("a count")?;
+                limit.max_events = Some(value.parse()
+                    .map_err(|_| format!("bad event count: {}", value))?);
+            }
+            "--until-robbed" => {
+                let value = value("an elf")?;
+                limit.until_robbed = Some(value.parse()
+                    .map_err(|_| format!("bad elf: {}", value))?);
+            }
+            _ => return Err(format!("unknown argument: {}", arg)),
+        }
+    }
+
+    let file = match file {
+        Some(file) => file,
+        None if limit == TraceLimit::default() => return Ok(None),
+        None => return Err("trace limits given without --trace".to_string()),
+    };
+    let format = if file.ends_with(".jsonl") {
+        TraceFormat::JsonLines
+    } else {
+        TraceFormat::Csv
+    };
+
+    Ok(Some(TraceArgs { file: file, format: format, limit: limit }))
+}
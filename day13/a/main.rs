@@ -1,131 +1,241 @@
-use std::collections::HashMap;
-use std::collections::LinkedList;
-use std::fs;
-use std::result::Result;
-use std::str::FromStr;
+#[path = "../../common/search.rs"]
+mod search;
+#[path = "../../common/render.rs"]
+mod render;
+#[path = "../../common/office_maze.rs"]
+mod office_maze;
 
-enum MazeSpace {
-    Wall,
-    Open(u32),
-}
+use std::fs;
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
-struct Coord {
-    x: u32,
-    y: u32,
-}
+use office_maze::Coord;
+use office_maze::Maze;
+use office_maze::Polynomial;
+use office_maze::Reach;
+use office_maze::OFFSETS;
+use office_maze::parse_bitmap;
+use office_maze::parse_maze;
 
 const START_COORD: Coord = Coord { x: 1, y: 1 };
 const END_COORD: Coord = Coord { x: 31, y: 39 };
 
-struct Maze {
-    spaces: HashMap<Coord, MazeSpace>,
-    origins: LinkedList<Coord>,
-    wall_param: u32,
-}
-
-impl Maze {
-    pub fn new(start_coord: Coord, wall_param: u32) -> Maze {
-        let mut new_maze = Maze {
-            spaces: HashMap::new(),
-            origins: LinkedList::new(),
-            wall_param: wall_param,
-        };
-        new_maze.spaces.insert(
-            start_coord,
-            MazeSpace::Open(0));
-        new_maze.origins.push_back(start_coord);
-        new_maze
+const IMAGE_FILE: &str = "maze.ppm";
+
+// Cap on how many spaces a reachability query may explore from each side.
+const MAX_QUERY_CELLS: usize = 1_000_000;
+
+fn do_tests() {
+    let parsed = Polynomial::parse("x^2 + 3x + 2xy + y + y^2 + 10").unwrap();
+    assert_eq!(parsed, Polynomial::office(10));
+
+    let parsed = Polynomial::parse("2*x^3*y + 7").unwrap();
+    assert_eq!(parsed.eval(Coord { x: 2, y: 3 }), Some(55));
+
+    assert!(Polynomial::parse("3z").is_err());
+    assert!(Polynomial::parse("x + + y").is_err());
+
+    let bitmap = parse_bitmap(concat!(
+        "#####\n",
+        "#...#\n",
+        "###.#\n",
+        "#...#\n",
+        "#####\n"));
+    assert!(bitmap.is_ok());
+    assert!(parse_bitmap("#?#").is_err());
+
+    let mut maze = Maze::new(START_COORD, 10);
+    assert_eq!(maze.dist_to(Coord { x: 7, y: 4 }, MAX_QUERY_CELLS), Ok(11));
+
+    let mut maze = Maze::from_bitmap(START_COORD, bitmap.unwrap());
+    assert_eq!(maze.dist_to(Coord { x: 1, y: 3 }, MAX_QUERY_CELLS), Ok(6));
+    assert!(maze.dist_to(Coord { x: 0, y: 0 }, MAX_QUERY_CELLS).is_err());
+
+    let mut maze = Maze::with_wall_fn(START_COORD,
+        |pos| pos.x == 2 && pos.y < 3);
+    assert_eq!(maze.dist_to(Coord { x: 3, y: 1 }, MAX_QUERY_CELLS), Ok(6));
+
+    let mut maze = Maze::from_bitmap(START_COORD, parse_bitmap(concat!(
+        "#####\n",
+        "#...#\n",
+        "###.#\n",
+        "#...#\n",
+        "#####\n")).unwrap());
+    let targets = [
+        Coord { x: 1, y: 3 },
+        Coord { x: 3, y: 1 },
+        Coord { x: 0, y: 0 },
+        Coord { x: 9, y: 9 },
+    ];
+    assert_eq!(maze.dists_to_all(&targets, 100), Ok(vec![
+        Reach::Dist(6), Reach::Dist(2), Reach::Unreachable, Reach::Unreachable,
+    ]));
+    assert_eq!(maze.cells_within(2).unwrap().len(), 3);
+    assert_eq!(maze.reachable_region(100).unwrap().map(|r| r.len()), Some(7));
+    assert_eq!(maze.farthest_reachable(100),
+        Ok(Some((Coord { x: 1, y: 3 }, 6))));
+    assert_eq!(maze.farthest_reachable(5), Ok(None));
+    assert_eq!(maze.farthest_reachable(6), Ok(None));
+    assert_eq!(maze.reachable_region(7).unwrap().map(|r| r.len()), Some(7));
+    assert_eq!(maze.farthest_reachable(7),
+        Ok(Some((Coord { x: 1, y: 3 }, 6))));
+    assert_eq!(maze.dists_to_all(&targets[..1], 7), Ok(vec![Reach::Dist(6)]));
+
+    // Open everywhere except a ring of walls around (10, 10).
+    let mut maze = Maze::with_wall_fn(START_COORD, |pos| {
+        let dx = (pos.x as i64 - 10).abs();
+        let dy = (pos.y as i64 - 10).abs();
+        std::cmp::max(dx, dy) == 1
+    });
+    assert_eq!(maze.is_reachable(Coord { x: 10, y: 10 }, 1000),
+        Ok(Reach::Unreachable));
+    assert_eq!(maze.is_reachable(Coord { x: 10, y: 10 }, 1),
+        Ok(Reach::Unreachable));
+    assert_eq!(maze.is_reachable(Coord { x: 5, y: 1 }, 1000),
+        Ok(Reach::Dist(4)));
+    assert_eq!(maze.is_reachable(Coord { x: 500, y: 500 }, 1000),
+        Ok(Reach::Unknown));
+    assert_eq!(maze.reachable_region(1000), Ok(None));
+
+    // With every step costing 1, dijkstra and astar with a Manhattan
+    // distance heuristic find the same distances as bfs.
+    let maze = Maze::new(START_COORD, 10);
+    let goal = Coord { x: 7, y: 4 };
+    let manhattan = |pos: &Coord| {
+        (pos.x.abs_diff(goal.x) + pos.y.abs_diff(goal.y)) as u32
+    };
+    let by_bfs = search::bfs(&maze, START_COORD, |pos| *pos == goal);
+    let by_dijkstra = search::dijkstra(&maze, START_COORD, |pos| *pos == goal);
+    let by_astar = search::astar(&maze, START_COORD, |pos| *pos == goal,
+        manhattan);
+    assert_eq!(by_bfs.goal_dist(), Some(11));
+    assert_eq!(by_dijkstra.goal_dist(), Some(11));
+    assert_eq!(by_astar.goal_dist(), Some(11));
+    assert_eq!(by_astar.goal_path().unwrap().len(), 12);
+
+    // Stepping onto a cell costs that cell's weight, so the cheapest route
+    // isn't the shortest. Checked against relaxing every edge until nothing
+    // improves.
+    use search::Neighbors;
+    struct Weighted {
+        costs: Vec<Vec<u32>>,
     }
-
-    fn offset_pos(pos: Coord, x_offset: i32, y_offset: i32) -> Option<Coord> {
-        if x_offset < 0 && (x_offset.abs() as u32) > pos.x {
-            return None;
+    impl search::Neighbors for Weighted {
+        type Node = Coord;
+
+        fn neighbors(&self, pos: &Coord) -> Vec<Coord> {
+            OFFSETS.iter()
+                .filter_map(|&(x_offset, y_offset)| {
+                    Maze::offset_pos(*pos, x_offset, y_offset)
+                })
+                .filter(|new_pos| new_pos.x < 8 && new_pos.y < 8)
+                .collect()
         }
 
-        if y_offset < 0 && (y_offset.abs() as u32) > pos.y {
-            return None;
+        fn step_cost(&self, _from: &Coord, to: &Coord) -> u32 {
+            self.costs[to.y as usize][to.x as usize]
         }
-
-        Some(
-            Coord {
-                x: pos.x.wrapping_add(x_offset as u32),
-                y: pos.y.wrapping_add(y_offset as u32),
-            })
     }
-
-    fn is_wall(pos: Coord, wall_param: u32) -> bool {
-        let mut val = (pos.x * pos.x) + (3 * pos.x) + (2 * pos.x * pos.y) + pos.y
-            + (pos.y * pos.y)
-            + wall_param;
-
-        let mut num_ones = 0;
-        while val != 0 {
-            if val & 1 == 1 {
-                num_ones += 1;
+    let weighted = Weighted {
+        costs: (0..8)
+            .map(|y| (0..8).map(|x| 1 + (x * 7 + y * 3) % 9).collect())
+            .collect(),
+    };
+
+    let start = Coord { x: 0, y: 0 };
+    let mut best = vec![vec![u32::MAX; 8]; 8];
+    best[0][0] = 0;
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for y in 0..8u64 {
+            for x in 0..8u64 {
+                let pos = Coord { x: x, y: y };
+                let dist = best[y as usize][x as usize];
+                if dist == u32::MAX {
+                    continue;
+                }
+                for next in weighted.neighbors(&pos) {
+                    let next_dist = dist + weighted.step_cost(&pos, &next);
+                    let next_best = &mut best[next.y as usize][next.x as usize];
+                    if next_dist < *next_best {
+                        *next_best = next_dist;
+                        improved = true;
+                    }
+                }
             }
-
-            val = val >> 1;
-        }
-
-        (num_ones & 1) == 1
-    }
-
-    fn process_offset(&mut self, pos: Coord, pos_dist: u32,
-        x_offset: i32, y_offset: i32) {
-
-        let new_pos = match Maze::offset_pos(pos, x_offset, y_offset) {
-            None => return,
-            Some(new_pos) => new_pos,
-        };
-
-        if self.spaces.contains_key(&new_pos) {
-            return;
-        }
-
-        if Maze::is_wall(new_pos, self.wall_param) {
-            self.spaces.insert(new_pos, MazeSpace::Wall);
-        } else {
-            self.spaces.insert(new_pos, MazeSpace::Open(pos_dist + 1));
-            self.origins.push_back(new_pos);
         }
     }
 
-    pub fn dist_to(&mut self, end_coord: Coord) -> Result<u32, &str> {
-        loop {
-            if let Some(space) = self.spaces.get(&end_coord) {
-                match space {
-                    MazeSpace::Wall => return Err("no solution: wall"),
-                    MazeSpace::Open(dist) => return Ok(*dist),
-                };
-            }
-
-            if self.origins.is_empty() {
-                return Err("no solution: unreachable");
-            }
-
-            let pos = self.origins.pop_front().unwrap();
-            let pos_dist = match *self.spaces.get(&pos).unwrap() {
-                MazeSpace::Wall => panic!("next origin was a wall"),
-                MazeSpace::Open(dist) => dist,
-            };
-
-            self.process_offset(pos, pos_dist,  0, -1);
-            self.process_offset(pos, pos_dist,  0,  1);
-            self.process_offset(pos, pos_dist, -1,  0);
-            self.process_offset(pos, pos_dist,  1,  0);
-        }
+    let everything = search::dijkstra(&weighted, start, |_| false);
+    assert!(everything.complete);
+    for (pos, dist) in everything.dists.iter() {
+        assert_eq!(*dist, best[pos.y as usize][pos.x as usize]);
     }
+    assert_eq!(everything.dists.len(), 64);
+
+    let corner = Coord { x: 7, y: 7 };
+    let to_corner = |pos: &Coord| {
+        (pos.x.abs_diff(corner.x) + pos.y.abs_diff(corner.y)) as u32
+    };
+    let result = search::astar(&weighted, start, |pos| *pos == corner,
+        to_corner);
+    assert_eq!(result.goal_dist(), Some(best[7][7]));
+    let path = result.goal_path().unwrap();
+    let path_cost: u32 = path.windows(2)
+        .map(|step| weighted.step_cost(&step[0], &step[1]))
+        .sum();
+    assert_eq!(path_cost, best[7][7]);
+    assert!(best[7][7] > 14);
+
+    // Past 65535 the office formula no longer fits in 32 bits.
+    let office = Polynomial::office(10);
+    let far = Coord { x: 70_000, y: 70_000 };
+    assert_eq!(office.eval(far), Some(4 * 70_000 * 70_000 + 4 * 70_000 + 10));
+
+    let corridor_start = Coord { x: 1, y: 1 };
+    let mut maze = Maze::with_wall_fn(corridor_start, |pos| pos.y != 1);
+    assert_eq!(maze.dist_to(Coord { x: 100_000, y: 1 }, MAX_QUERY_CELLS),
+        Ok(99_999));
+
+    let huge_start = Coord { x: 1 << 40, y: 0 };
+    let mut maze = Maze::from_polynomial(huge_start,
+        Polynomial::parse("x^4").unwrap());
+    assert!(maze.dist_to(Coord { x: (1 << 40) + 2, y: 0 }, MAX_QUERY_CELLS)
+        .is_err());
+
+    // The start is in an endless open plane, so only the search from the
+    // target side can finish.
+    let walled_in = Coord { x: 50, y: 50 };
+    let mut maze = Maze::with_wall_fn(START_COORD, move |pos| {
+        let dx = (pos.x as i64 - walled_in.x as i64).abs();
+        let dy = (pos.y as i64 - walled_in.y as i64).abs();
+        dx.max(dy) == 1
+    });
+    assert_eq!(maze.route_to(walled_in, 1000),
+        Err("no solution: unreachable".to_string()));
+    assert_eq!(maze.dist_to(Coord { x: 60, y: 50 }, 100_000), Ok(108));
+
+    let mut maze = Maze::with_wall_fn(START_COORD, |pos| pos.x == 5);
+    assert_eq!(maze.dist_to(Coord { x: 10, y: 1 }, 1000),
+        Err("no solution: unknown, gave up after 1000 cells".to_string()));
 }
 
 fn main() {
+    do_tests();
+
     let input = fs::read_to_string("input.txt")
         .expect("Something went wrong reading the file");
 
-    let wall_param = u32::from_str(input.trim()).unwrap();
-    println!("input: {}", wall_param);
+    println!("input: {}", input.lines().next().unwrap_or("").trim());
 
-    let mut maze = Maze::new(START_COORD, wall_param);
-    let dist = maze.dist_to(END_COORD).unwrap();
+    let mut maze = parse_maze(&input, START_COORD).unwrap();
+    let dist = maze.dist_to(END_COORD, MAX_QUERY_CELLS).unwrap();
     println!("dist: {}", dist);
+    println!("reachable: {:?}",
+        maze.is_reachable(END_COORD, MAX_QUERY_CELLS).unwrap());
+
+    let route = maze.route_to(END_COORD, MAX_QUERY_CELLS).unwrap();
+    print!("{}", maze.render_ascii(&route));
+    fs::write(IMAGE_FILE, maze.render_ppm(&route))
+        .expect("Something went wrong writing the image");
+    println!("wrote {}", IMAGE_FILE);
 }
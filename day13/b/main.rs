@@ -1,136 +1,106 @@
-use std::collections::HashMap;
-use std::collections::LinkedList;
-use std::fs;
-use std::str::FromStr;
+#[path = "../../common/search.rs"]
+mod search;
+#[path = "../../common/render.rs"]
+mod render;
+#[path = "../../common/office_maze.rs"]
+mod office_maze;
 
-enum MazeSpace {
-    Wall,
-    Open(u32),
-}
+use std::fs;
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
-struct Coord {
-    x: u32,
-    y: u32,
-}
+use office_maze::Coord;
+use office_maze::Maze;
+use office_maze::Polynomial;
+use office_maze::parse_bitmap;
+use office_maze::parse_maze;
 
 const START_COORD: Coord = Coord { x: 1, y: 1 };
 const MAX_DIST: u32 = 50;
 
-struct Maze {
-    spaces: HashMap<Coord, MazeSpace>,
-    origins: LinkedList<Coord>,
-    wall_param: u32,
+const IMAGE_FILE: &str = "maze.ppm";
+
+// Cap on how many spaces a reachability query may explore from each side.
+const MAX_QUERY_CELLS: usize = 1_000_000;
+
+fn do_tests() {
+    let parsed = Polynomial::parse("x^2 + 3x + 2xy + y + y^2 + 10").unwrap();
+    assert_eq!(parsed, Polynomial::office(10));
+
+    let parsed = Polynomial::parse("2*x^3*y + 7").unwrap();
+    assert_eq!(parsed.eval(Coord { x: 2, y: 3 }), Some(55));
+
+    assert!(Polynomial::parse("3z").is_err());
+    assert!(Polynomial::parse("x + + y").is_err());
+
+    let bitmap = parse_bitmap(concat!(
+        "#####\n",
+        "#...#\n",
+        "###.#\n",
+        "#...#\n",
+        "#####\n"));
+    assert!(bitmap.is_ok());
+    assert!(parse_bitmap("#?#").is_err());
+
+    let mut maze = Maze::from_bitmap(START_COORD, bitmap.unwrap());
+    maze.fill_to_dist(2).unwrap();
+    assert_eq!(maze.num_marked_reachable(2), 3);
+    maze.fill_to_dist(100).unwrap();
+    assert_eq!(maze.num_marked_reachable(100), 7);
+    assert_eq!(maze.reachable_region(6), Ok(None));
+    assert_eq!(maze.reachable_region(7).unwrap().map(|r| r.len()), Some(7));
+    assert_eq!(maze.farthest_reachable(7),
+        Ok(Some((Coord { x: 1, y: 3 }, 6))));
+
+    let mut maze = Maze::new(START_COORD, 10);
+    maze.fill_to_dist(10).unwrap();
+    let num_reachable = maze.num_marked_reachable(10) as usize;
+    assert_eq!(maze.cells_within(10).unwrap().len(), num_reachable);
+
+    let mut maze = Maze::with_wall_fn(START_COORD, |pos| pos.x > 3);
+    assert_eq!(maze.reachable_region(100), Ok(None));
+    assert_eq!(maze.farthest_reachable(100), Ok(None));
+    let mut maze = Maze::with_wall_fn(START_COORD,
+        |pos| pos.x > 3 || pos.y > 2);
+    assert_eq!(maze.reachable_region(100).unwrap().map(|r| r.len()), Some(12));
+    assert_eq!(maze.farthest_reachable(100),
+        Ok(Some((Coord { x: 3, y: 0 }, 3))));
+
+    // Past 65535 the office formula no longer fits in 32 bits.
+    let office = Polynomial::office(10);
+    let far = Coord { x: 70_000, y: 70_000 };
+    assert_eq!(office.eval(far), Some(4 * 70_000 * 70_000 + 4 * 70_000 + 10));
+
+    let corridor_start = Coord { x: 1, y: 1 };
+    let mut maze = Maze::with_wall_fn(corridor_start, |pos| pos.y != 1);
+    assert_eq!(maze.cells_within(100_000).unwrap().len(), 100_002);
+
+    let huge_start = Coord { x: 1 << 40, y: 0 };
+    let mut maze = Maze::from_polynomial(huge_start,
+        Polynomial::parse("x^4").unwrap());
+    assert!(maze.farthest_reachable(100).is_err());
 }
 
-impl Maze {
-    pub fn new(start_coord: Coord, wall_param: u32) -> Maze {
-        let mut new_maze = Maze {
-            spaces: HashMap::new(),
-            origins: LinkedList::new(),
-            wall_param: wall_param,
-        };
-        new_maze.spaces.insert(
-            start_coord,
-            MazeSpace::Open(0));
-        new_maze.origins.push_back(start_coord);
-        new_maze
-    }
-
-    fn offset_pos(pos: Coord, x_offset: i32, y_offset: i32) -> Option<Coord> {
-        if x_offset < 0 && (x_offset.abs() as u32) > pos.x {
-            return None;
-        }
-
-        if y_offset < 0 && (y_offset.abs() as u32) > pos.y {
-            return None;
-        }
-
-        Some(
-            Coord {
-                x: pos.x.wrapping_add(x_offset as u32),
-                y: pos.y.wrapping_add(y_offset as u32),
-            })
-    }
-
-    fn is_wall(pos: Coord, wall_param: u32) -> bool {
-        let mut val = (pos.x * pos.x) + (3 * pos.x) + (2 * pos.x * pos.y) + pos.y
-            + (pos.y * pos.y)
-            + wall_param;
-
-        let mut num_ones = 0;
-        while val != 0 {
-            if val & 1 == 1 {
-                num_ones += 1;
-            }
-
-            val = val >> 1;
-        }
-
-        (num_ones & 1) == 1
-    }
-
-    fn process_offset(&mut self, pos: Coord, pos_dist: u32,
-        x_offset: i32, y_offset: i32) {
+fn main() {
+    do_tests();
 
-        let new_pos = match Maze::offset_pos(pos, x_offset, y_offset) {
-            None => return,
-            Some(new_pos) => new_pos,
-        };
+    let input = fs::read_to_string("input.txt")
+        .expect("Something went wrong reading the file");
 
-        if self.spaces.contains_key(&new_pos) {
-            return;
-        }
+    println!("input: {}", input.lines().next().unwrap_or("").trim());
 
-        if Maze::is_wall(new_pos, self.wall_param) {
-            self.spaces.insert(new_pos, MazeSpace::Wall);
-        } else {
-            self.spaces.insert(new_pos, MazeSpace::Open(pos_dist + 1));
-            self.origins.push_back(new_pos);
-        }
-    }
+    let mut maze = parse_maze(&input, START_COORD).unwrap();
 
-    pub fn fill_to_dist(&mut self, max_dist: u32) {
-        loop {
-            if self.origins.is_empty() {
-                return;
-            }
-
-            let pos = self.origins.pop_front().unwrap();
-            let pos_dist = match *self.spaces.get(&pos).unwrap() {
-                MazeSpace::Wall => panic!("next origin was a wall"),
-                MazeSpace::Open(dist) => dist,
-            };
-
-            if pos_dist < max_dist {
-                self.process_offset(pos, pos_dist,  0, -1);
-                self.process_offset(pos, pos_dist,  0,  1);
-                self.process_offset(pos, pos_dist, -1,  0);
-                self.process_offset(pos, pos_dist,  1,  0);
-            }
-        }
+    match maze.farthest_reachable(MAX_QUERY_CELLS).unwrap() {
+        Some((pos, dist)) => println!("farthest reachable: {},{} at {}",
+            pos.x, pos.y, dist),
+        None => println!("open region has more than {} spaces",
+            MAX_QUERY_CELLS),
     }
 
-    pub fn num_marked_reachable(&self) -> u32 {
-        let mut num_spaces = 0;
-        for space in self.spaces.values() {
-            match *space {
-                MazeSpace::Open(_) => { num_spaces += 1},
-                _ => {},
-            }
-        }
-        num_spaces
-    }
-}
-
-fn main() {
-    let input = fs::read_to_string("input.txt")
-        .expect("Something went wrong reading the file");
-
-    let wall_param = u32::from_str(input.trim()).unwrap();
-    println!("input: {}", wall_param);
+    maze.fill_to_dist(MAX_DIST).unwrap();
+    println!("num reachable: {}", maze.num_marked_reachable(MAX_DIST));
 
-    let mut maze = Maze::new(START_COORD, wall_param);
-    maze.fill_to_dist(MAX_DIST);
-    println!("num reachable: {}", maze.num_marked_reachable());
+    print!("{}", maze.render_ascii(&[]));
+    fs::write(IMAGE_FILE, maze.render_ppm(&[]))
+        .expect("Something went wrong writing the image");
+    println!("wrote {}", IMAGE_FILE);
 }
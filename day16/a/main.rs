@@ -1,62 +1,170 @@
-use std::cmp;
 // use std::collections::HashSet;
 // use std::collections::LinkedList;
 // use std::fs;
 // use std::result::Result;
-use std::str;
+// use std::str;
 // use std::str::FromStr;
 
-fn str_to_vec(s: &str) -> Vec<u8> {
-    s.chars()
-        .map(|c| {
-            match c {
-                '0' => 0,
-                '1' => 1,
-                _ => panic!("bad char"),
-            }
-        })
-        .collect()
+// A string of bits packed 64 to a word, with bit i in bit i % 64 of word
+// i / 64. Bits past `len` in the last word are always 0.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+struct Bits {
+    words: Vec<u64>,
+    len: usize,
 }
 
-fn vec_to_str(v: &Vec<u8>) -> String {
-    let chars = v.iter()
-        .map(|num| {
-            match num {
-                0 => b'0',
-                1 => b'1',
-                _ => panic!("bad num: {}", num),
+impl Bits {
+    fn with_capacity(num_bits: usize) -> Bits {
+        Bits {
+            words: Vec::with_capacity(num_bits.div_ceil(64)),
+            len: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, idx: usize) -> u8 {
+        ((self.words[idx / 64] >> (idx % 64)) & 1) as u8
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..self.len).map(move |idx| self.get(idx))
+    }
+
+    fn push(&mut self, bit: u8) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        let bit = ((bit & 1) as u64) << (self.len % 64);
+        *self.words.last_mut().unwrap() |= bit;
+        self.len += 1;
+    }
+
+    // Appends all of `other` a word at a time.
+    fn append(&mut self, other: &Bits) {
+        let shift = self.len % 64;
+        if shift == 0 {
+            self.words.extend_from_slice(&other.words);
+        } else {
+            for word in &other.words {
+                *self.words.last_mut().unwrap() |= word << shift;
+                self.words.push(word >> (64 - shift));
             }
-        })
-        .collect::<Vec<u8>>();
+        }
 
-    str::from_utf8(&chars).unwrap().to_string()
-}
+        self.len += other.len;
+        self.words.truncate(self.len.div_ceil(64));
+    }
 
-fn expand_data(data: &mut Vec<u8>, max_len: usize) {
-    if data.len() >= max_len {
-        return;
+    fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        self.len = len;
+        self.words.truncate(len.div_ceil(64));
+        self.clear_tail();
     }
 
-    let orig_len = data.len();
-    data.push(0);
+    fn clear_tail(&mut self) {
+        if !self.len.is_multiple_of(64) {
+            *self.words.last_mut().unwrap() &= (1 << (self.len % 64)) - 1;
+        }
+    }
+
+    // The bits in reverse order with each one flipped. Reversing the words
+    // and the bits within each word reverses the whole padded string, which
+    // leaves the result shifted up by the amount of padding.
+    fn reverse_complement(&self) -> Bits {
+        let pad = self.words.len() * 64 - self.len;
+        let mut words: Vec<u64> = self.words.iter().rev()
+            .map(|word| !word.reverse_bits())
+            .collect();
+
+        if pad > 0 {
+            for idx in 0..words.len() {
+                let next = words.get(idx + 1).cloned().unwrap_or(0);
+                words[idx] = (words[idx] >> pad) | (next << (64 - pad));
+            }
+        }
 
-    let mut i = orig_len - 1;
-    while data.len() < max_len {
-        data.push(data[i] ^ 1);
+        let mut reversed = Bits {
+            words: words,
+            len: self.len,
+        };
+        reversed.clear_tail();
+        reversed
+    }
 
-        if i == 0 {
-            break;
+    // XNOR of each pair of bits, 32 pairs from each word. Assumes an even
+    // number of bits.
+    fn xnor_pairs(&self) -> Bits {
+        let half_words = self.words.iter()
+            .map(|word| squeeze_even_bits(!(word ^ (word >> 1))))
+            .collect::<Vec<u64>>();
+
+        let mut words = Vec::with_capacity(half_words.len().div_ceil(2));
+        for pair in half_words.chunks(2) {
+            words.push(pair[0] | (pair.get(1).cloned().unwrap_or(0) << 32));
         }
 
-        i -= 1;
+        let mut paired = Bits {
+            words: words,
+            len: self.len / 2,
+        };
+        paired.words.truncate(paired.len.div_ceil(64));
+        paired.clear_tail();
+        paired
+    }
+}
+
+// Moves bits 0, 2, 4, ... 62 of `word` down into its low 32 bits.
+fn squeeze_even_bits(word: u64) -> u64 {
+    let mut x = word & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x >> 16)) & 0x0000_0000_ffff_ffff;
+    x
+}
+
+fn str_to_bits(s: &str) -> Bits {
+    let mut bits = Bits::with_capacity(s.len());
+    for c in s.chars() {
+        bits.push(match c {
+            '0' => 0,
+            '1' => 1,
+            _ => panic!("bad char"),
+        });
     }
+
+    bits
 }
 
-fn generate_data(data: &Vec<u8>, amount: usize) -> Vec<u8> {
-    let mut new_data = Vec::with_capacity(amount);
+fn bits_to_str(bits: &Bits) -> String {
+    bits.iter()
+        .map(|bit| if bit == 0 { '0' } else { '1' })
+        .collect()
+}
+
+fn expand_data(data: &mut Bits, max_len: usize) {
+    if data.len() >= max_len {
+        return;
+    }
+
+    let reversed = data.reverse_complement();
+    data.push(0);
+    data.append(&reversed);
+    data.truncate(max_len);
+}
 
-    let amt_to_copy = cmp::min(amount, data.len());
-    new_data.extend_from_slice(&data[0..amt_to_copy]);
+fn generate_data(data: &Bits, amount: usize) -> Bits {
+    let mut new_data = Bits::with_capacity(amount);
+    new_data.append(data);
+    new_data.truncate(amount);
 
     while new_data.len() < amount {
         expand_data(&mut new_data, amount);
@@ -65,60 +173,194 @@ fn generate_data(data: &Vec<u8>, amount: usize) -> Vec<u8> {
     new_data
 }
 
-fn checksum_data(data: &Vec<u8>) -> Vec<u8> {
+fn checksum_data(data: &Bits) -> Bits {
     if data.len() % 2 == 1 {
         panic!("checksum data len is odd");
     }
 
-    let mut chksum = Vec::with_capacity(data.len() / 2);
+    let mut chksum = data.xnor_pairs();
+    while chksum.len().is_multiple_of(2) && chksum.len() > 0 {
+        chksum = chksum.xnor_pairs();
+    }
+
+    chksum
+}
+
+// The disk data is the seed and its reverse complement taking turns, with
+// one separator bit after each:
+//
+//     a s1 b s2 a s3 b s4 a ...
+//
+// The separators s1, s2, ... are the dragon curve sequence: s(j) is 1 when
+// the odd part of j is 3 mod 4. That makes it possible to count the 1s in
+// any prefix of the disk, and so the parity of any range of it, without
+// generating anything.
+struct DragonDisk {
+    // seed_parity[i] is the parity of the first i bits of the seed, and
+    // rev_parity[i] the same for its reverse complement.
+    seed_parity: Vec<u8>,
+    rev_parity: Vec<u8>,
+}
+
+impl DragonDisk {
+    fn new(seed: &Bits) -> DragonDisk {
+        assert!(seed.len() > 0, "seed is empty");
 
-    let mut i = 0;
-    while i < data.len() {
-        chksum.push(data[i] ^ data[i + 1] ^ 1);
-        i += 2;
+        let prefix_parity = |bits: &mut dyn Iterator<Item = u8>| {
+            let mut parity = vec![0];
+            for bit in bits {
+                parity.push(parity.last().unwrap() ^ bit);
+            }
+            parity
+        };
+
+        DragonDisk {
+            seed_parity: prefix_parity(&mut seed.iter()),
+            rev_parity: prefix_parity(&mut seed.reverse_complement().iter()),
+        }
+    }
+
+    // Parity of the number of 1s in the first `len` bits of the disk.
+    fn prefix_parity(&self, len: u64) -> u8 {
+        let seed_len = self.seed_parity.len() as u64 - 1;
+        let num_blocks = len / (seed_len + 1);
+        let partial_len = (len % (seed_len + 1)) as usize;
+
+        let seed_ones = self.seed_parity[seed_len as usize];
+        let rev_ones = self.rev_parity[seed_len as usize];
+
+        // Whole blocks alternate seed, reverse, seed, ... so an odd count of
+        // either only matters when the number of blocks of it is odd.
+        let num_seeds = num_blocks.div_ceil(2);
+        let num_revs = num_blocks / 2;
+        let mut parity = (seed_ones & num_seeds as u8 & 1)
+            ^ (rev_ones & num_revs as u8 & 1)
+            ^ separator_parity(num_blocks);
+
+        parity ^= if num_blocks.is_multiple_of(2) {
+            self.seed_parity[partial_len]
+        } else {
+            self.rev_parity[partial_len]
+        };
+
+        parity
     }
+}
 
-    if chksum.len() % 2 == 0 {
-        chksum = checksum_data(&chksum);
+// Parity of the number of 1s among the first `num_seps` separators. The
+// separators at j = 2^t * o with o odd are 1 when o is 3 mod 4, and for each t
+// there are (num_seps / 2^t + 1) / 4 of those.
+fn separator_parity(num_seps: u64) -> u8 {
+    let mut parity = 0;
+    let mut odd_max = num_seps;
+    while odd_max > 0 {
+        parity ^= ((odd_max / 4 + (odd_max % 4 == 3) as u64) & 1) as u8;
+        odd_max /= 2;
+    }
+
+    parity
+}
+
+// The same checksum as `checksum_data(&generate_data(seed, disk_len))`.
+// Folding pairs with XNOR until the length is odd works out to one checksum
+// bit per chunk of the disk, 1 when the chunk has an even number of 1s, where
+// the chunk size is the largest power of two dividing the disk length. Each
+// of those only needs the parity of two disk prefixes, so this takes memory
+// for just the checksum itself.
+fn checksum_streaming(seed: &Bits, disk_len: u64) -> Bits {
+    if disk_len % 2 == 1 {
+        panic!("checksum data len is odd");
+    }
+    if disk_len == 0 {
+        return Bits::default();
+    }
+
+    let disk = DragonDisk::new(seed);
+    let chunk_len = 1u64 << disk_len.trailing_zeros();
+    let num_chunks = disk_len / chunk_len;
+
+    let mut chksum = Bits::with_capacity(num_chunks as usize);
+    for chunk in 0..num_chunks {
+        let start = chunk * chunk_len;
+        chksum.push(disk.prefix_parity(start)
+            ^ disk.prefix_parity(start + chunk_len) ^ 1);
     }
 
     chksum
 }
 
 fn do_tests() {
-    let result = str_to_vec("001101");
-    assert_eq!(&result, &vec![0, 0, 1, 1, 0, 1]);
+    let result = str_to_bits("001101");
+    assert_eq!(result.len(), 6);
+    assert_eq!(result.words, vec![0b101100]);
 
-    let result = vec_to_str(&vec![0, 0, 1, 1, 0, 1]);
+    let result = bits_to_str(&str_to_bits("001101"));
     assert_eq!(&result, "001101");
 
-    let result = generate_data(&vec![1], 3);
-    assert_eq!(&result, &vec![1, 0, 0]);
+    let result = generate_data(&str_to_bits("1"), 3);
+    assert_eq!(&result, &str_to_bits("100"));
+
+    let result = generate_data(&str_to_bits("1"), 2);
+    assert_eq!(&result, &str_to_bits("10"));
 
-    let result = generate_data(&vec![1], 2);
-    assert_eq!(&result, &vec![1, 0]);
+    let result = generate_data(&str_to_bits("1"), 1);
+    assert_eq!(&result, &str_to_bits("1"));
 
-    let result = generate_data(&vec![1], 1);
-    assert_eq!(&result, &vec![1]);
+    let result = generate_data(&str_to_bits("0"), 3);
+    assert_eq!(&result, &str_to_bits("001"));
 
-    let result = generate_data(&vec![0], 3);
-    assert_eq!(&result, &vec![0, 0, 1]);
+    let result = generate_data(&str_to_bits("111100001010"), 25);
+    assert_eq!(&result, &str_to_bits("1111000010100101011110000"));
 
-    let result = generate_data(&str_to_vec("111100001010"), 25);
-    assert_eq!(&result, &str_to_vec("1111000010100101011110000"));
+    let result = checksum_data(&str_to_bits("110010110100"));
+    assert_eq!(&result, &str_to_bits("100"));
+
+    let result = checksum_streaming(&str_to_bits("10000"), 20);
+    assert_eq!(&result, &str_to_bits("01100"));
+
+    // Lengths on both sides of word boundaries, checked a bit at a time.
+    let pattern = "1101001110010111011000101";
+    for len in [1, 63, 64, 65, 127, 128, 130, 200].iter().cloned() {
+        let s: String = pattern.chars().cycle().take(len).collect();
+        let bits = str_to_bits(&s);
+
+        let reversed: String = s.chars().rev()
+            .map(|c| if c == '0' { '1' } else { '0' })
+            .collect();
+        assert_eq!(bits.reverse_complement(), str_to_bits(&reversed));
+
+        let mut doubled = bits.clone();
+        doubled.append(&bits);
+        assert_eq!(doubled, str_to_bits(&format!("{}{}", s, s)));
+
+        if len % 2 == 0 {
+            let paired: String = s.as_bytes().chunks(2)
+                .map(|pair| if pair[0] == pair[1] { '1' } else { '0' })
+                .collect();
+            assert_eq!(bits.xnor_pairs(), str_to_bits(&paired));
+        }
+    }
+
+    for seed in &["0", "1", "10000", "111100001010", "01000100010010111"] {
+        let seed = str_to_bits(seed);
+        for disk_len in (2..600).step_by(2) {
+            let data = generate_data(&seed, disk_len);
+            assert_eq!(checksum_streaming(&seed, disk_len as u64),
+                checksum_data(&data));
+        }
+    }
 
-    let result = checksum_data(&str_to_vec("110010110100"));
-    assert_eq!(&result, &str_to_vec("100"));
+    let result = checksum_streaming(&str_to_bits("10000"), 3 << 62);
+    assert_eq!(result.len(), 3);
 }
 
 fn main() {
-    const DISK_LEN: usize = 272;
+    const DISK_LEN: u64 = 272;
     const INPUT: &str = "01000100010010111";
 
-    let data = generate_data(&str_to_vec(INPUT), DISK_LEN);
-    let checksum = checksum_data(&data);
+    let checksum = checksum_streaming(&str_to_bits(INPUT), DISK_LEN);
 
-    println!("checksum: {}", vec_to_str(&checksum));
+    println!("checksum: {}", bits_to_str(&checksum));
 
     do_tests();
 }
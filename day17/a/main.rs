@@ -1,5 +1,7 @@
+#[path = "../../common/search.rs"]
+mod search;
+
 // use std::collections::HashSet;
-use std::collections::LinkedList;
 // use std::fs;
 // use std::result::Result;
 use std::str;
@@ -8,6 +10,9 @@ use std::str;
 const MAZE_WIDTH: u32 = 4;
 const MAZE_HEIGHT: u32 = 4;
 
+const START_POS: Coord = Coord { x: 0, y: 0 };
+const END_POS: Coord = Coord { x: 3, y: 3 };
+
 // const NUM_HEX_CHARS: u32 = 16;
 
 const MD5_CHUNK_LEN: usize = 512 / 8;
@@ -30,7 +35,7 @@ impl Dir {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct Coord {
     x: u32,
     y: u32,
@@ -42,7 +47,7 @@ struct Move {
     result_pos: Coord,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 struct Trail {
     path: Vec<u8>,
     end: Coord,
@@ -316,43 +321,49 @@ fn find_valid_moves(passwd: &[u8], path: &[u8], coord: Coord) -> Vec<Move> {
     moves
 }
 
-fn search_maze(passwd: &str) -> Option<String> {
-    const START_POS: Coord = Coord { x: 0, y: 0 };
-    const END_POS: Coord = Coord { x: 3, y: 3 };
-
-    let mut trails = LinkedList::new();
-    trails.push_back(
-        Trail {
-            path: vec![],
-            end: START_POS,
-        });
-
-    loop {
-        if trails.is_empty() {
-            return None
-        }
+// The doors depend on the whole path taken so far, so each node of the
+// search is a complete trail rather than just a room. Reaching the vault ends
+// a trail.
+struct Vault<'a> {
+    passwd: &'a [u8],
+}
 
-        let cur_trail = trails.pop_front().unwrap();
-        if cur_trail.end == END_POS {
-            return Some(str::from_utf8(&cur_trail.path).unwrap().to_string());
+impl search::Neighbors for Vault<'_> {
+    type Node = Trail;
+
+    fn neighbors(&self, trail: &Trail) -> Vec<Trail> {
+        if trail.end == END_POS {
+            return vec![];
         }
 
-        let next_moves = find_valid_moves(
-            passwd.as_bytes(), &cur_trail.path, cur_trail.end);
+        let next_moves = find_valid_moves(self.passwd, &trail.path, trail.end);
         // println!("next_moves: {:?}", next_moves);
 
-        for next_move in next_moves {
-            let mut next_path = cur_trail.path.clone();
-            next_path.push(next_move.dir.to_byte());
-            trails.push_back(
+        next_moves.into_iter()
+            .map(|next_move| {
+                let mut next_path = trail.path.clone();
+                next_path.push(next_move.dir.to_byte());
                 Trail {
                     path: next_path,
                     end: next_move.result_pos,
-                });
-        }
+                }
+            })
+            .collect()
     }
 }
 
+fn search_maze(passwd: &str) -> Option<String> {
+    let vault = Vault { passwd: passwd.as_bytes() };
+    let start = Trail {
+        path: vec![],
+        end: START_POS,
+    };
+
+    let result = search::bfs(&vault, start, |trail| trail.end == END_POS);
+    result.goal
+        .map(|trail| str::from_utf8(&trail.path).unwrap().to_string())
+}
+
 fn do_tests() {
     let test_md5 = calc_md5("abcdefghijklmnopqrstuvwxyz".as_bytes());
     assert!(md5_bytes_as_str(&test_md5) == "c3fcd3d76192e4007dfb496cca67e13b");
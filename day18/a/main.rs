@@ -1,3 +1,8 @@
+#[path = "../../common/automaton.rs"]
+mod automaton;
+
+use automaton::Automaton;
+use automaton::Boundary;
 // use std::collections::HashSet;
 // use std::collections::LinkedList;
 use std::fs;
@@ -46,16 +51,25 @@ fn get_affecting_tiles(row: &Row, idx: usize) -> u8 {
     tiles
 }
 
+// A tile becomes a trap when exactly one of the tiles to its left and right
+// is a trap, which is rule 90, with the walls past both ends counting as safe.
+const TRAP_RULES: Automaton = Automaton {
+    rule: 90,
+    boundary: Boundary::Dead,
+};
+
 fn get_next_row_tile(affecting_tiles: u8) -> Tile {
-    match affecting_tiles {
-        0b110 |
-        0b011 |
-        0b100 |
-        0b001 => Tile::Trap,
-        _ => Tile::Safe,
+    if TRAP_RULES.next_state(affecting_tiles) {
+        Tile::Trap
+    } else {
+        Tile::Safe
     }
 }
 
+fn row_to_cells(row: &Row) -> Vec<bool> {
+    row.iter().map(|tile| *tile == Tile::Trap).collect()
+}
+
 fn generate_next_row(row: &Row) -> Row {
     (0..row.len())
         .map(|n| get_affecting_tiles(row, n))
@@ -67,6 +81,330 @@ fn count_tile_type(row: &Row, tile_type: Tile) -> u32 {
     row.iter().filter(|tile| **tile == tile_type).count() as u32
 }
 
+// Use AVX2 for the word operations in `BitRow::next_row` when the CPU has it.
+const USE_SIMD: bool = true;
+
+// Also find where the rows start to repeat, and extrapolate the count to
+// HUGE_ROW_COUNT rows if the rows up to the end of the first cycle number no
+// more than MAX_GENERATED_ROWS.
+const COUNT_HUGE: bool = true;
+const MAX_GENERATED_ROWS: u64 = 100_000_000;
+
+// Draw the first rows as text and as a PBM image, with traps in black.
+const PRINT_ROWS: bool = true;
+const IMAGE_FILE: &str = "traps.pbm";
+const IMAGE_ROWS: usize = 1000;
+
+// A row with one bit per tile, set for traps. Tile i is bit i % 64 of
+// words[i / 64 + 1]. The words at either end and any bits past the end of
+// the row stay 0, so the walls beyond both ends read as safe.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct BitRow {
+    words: Vec<u64>,
+    width: usize,
+}
+
+impl BitRow {
+    fn new(width: usize) -> BitRow {
+        BitRow {
+            words: vec![0; width.div_ceil(64) + 2],
+            width: width,
+        }
+    }
+
+    fn from_row(row: &Row) -> BitRow {
+        let mut bit_row = BitRow::new(row.len());
+        for (idx, tile) in row.iter().enumerate() {
+            if *tile == Tile::Trap {
+                bit_row.words[idx / 64 + 1] |= 1 << (idx % 64);
+            }
+        }
+
+        bit_row
+    }
+
+    fn to_row(&self) -> Row {
+        (0..self.width)
+            .map(|idx| if self.get(idx) { Tile::Trap } else { Tile::Safe })
+            .collect()
+    }
+
+    fn get(&self, idx: usize) -> bool {
+        (self.words[idx / 64 + 1] >> (idx % 64)) & 1 == 1
+    }
+
+    // Rule 90 with safe walls past both ends acts like rule 90 on a ring of
+    // the row, a safe tile, the row reversed and another safe tile. The ring
+    // stays symmetric, so those two safe tiles stay safe.
+    fn ring_len(&self) -> usize {
+        2 * (self.width + 1)
+    }
+
+    fn ring_tile(&self, ring_idx: usize) -> bool {
+        if ring_idx == 0 || ring_idx == self.width + 1 {
+            false
+        } else if ring_idx <= self.width {
+            self.get(ring_idx - 1)
+        } else {
+            self.get(self.ring_len() - ring_idx - 1)
+        }
+    }
+
+    // The row `num_rows` rows further down. On the ring, a tile 2^k rows
+    // down is the XOR of the tiles 2^k places to either side of it, since
+    // (x + 1/x)^(2^k) = x^(2^k) + 1/x^(2^k) over GF(2), so this takes one
+    // pass over the row per bit of `num_rows`.
+    fn skip(&self, num_rows: u64) -> BitRow {
+        let ring_len = self.ring_len();
+        let mut row = self.clone();
+        let mut shift = 1 % ring_len;
+        let mut rows_left = num_rows;
+
+        while rows_left > 0 {
+            if rows_left & 1 == 1 {
+                let mut next = BitRow::new(self.width);
+                for idx in 0..self.width {
+                    let ring_idx = idx + 1;
+                    let left = (ring_idx + ring_len - shift) % ring_len;
+                    let right = (ring_idx + shift) % ring_len;
+                    if row.ring_tile(left) != row.ring_tile(right) {
+                        next.words[idx / 64 + 1] |= 1 << (idx % 64);
+                    }
+                }
+                row = next;
+            }
+
+            shift = shift * 2 % ring_len;
+            rows_left >>= 1;
+        }
+
+        row
+    }
+
+    fn num_safe(&self) -> u32 {
+        let num_traps: u32 = self.words.iter()
+            .map(|word| word.count_ones())
+            .sum();
+        self.width as u32 - num_traps
+    }
+
+    // Every trap pattern in `get_next_row_tile` comes down to the tiles to
+    // the left and right differing, so the next row is this one shifted left
+    // XOR this one shifted right.
+    fn next_row(&self, next: &mut BitRow, use_simd: bool) {
+        let num_words = self.words.len() - 2;
+        let mut idx = 1;
+        assert_eq!(next.words.len(), self.words.len());
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if use_simd && is_x86_feature_detected!("avx2") {
+                // SAFETY: the feature check above means the CPU has the AVX2
+                // instructions next_words_avx2 is compiled for. It only loads
+                // four words from `idx - 1`, `idx` and `idx + 1` while
+                // `idx + 5 <= words.len()`, and stores four from `idx` into
+                // `next`, which the assert above makes the same length.
+                idx = unsafe { next_words_avx2(&self.words, &mut next.words) };
+            }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = use_simd;
+
+        while idx <= num_words {
+            next.words[idx] = next_word(&self.words, idx);
+            idx += 1;
+        }
+
+        if !self.width.is_multiple_of(64) {
+            next.words[num_words] &= (1 << (self.width % 64)) - 1;
+        }
+    }
+}
+
+fn next_word(words: &[u64], idx: usize) -> u64 {
+    let from_left = (words[idx] << 1) | (words[idx - 1] >> 63);
+    let from_right = (words[idx] >> 1) | (words[idx + 1] << 63);
+    from_left ^ from_right
+}
+
+// Fills in four words at a time, for as long as the words on both sides of
+// each group are there to read, and returns the index of the first word it
+// didn't fill.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn next_words_avx2(words: &[u64], next: &mut [u64]) -> usize {
+    use std::arch::x86_64::*;
+
+    let mut idx = 1;
+    while idx + 5 <= words.len() {
+        let load = |start: usize| {
+            _mm256_loadu_si256(words[start..].as_ptr() as *const __m256i)
+        };
+        let prev = load(idx - 1);
+        let cur = load(idx);
+        let after = load(idx + 1);
+
+        let from_left = _mm256_or_si256(_mm256_slli_epi64(cur, 1),
+            _mm256_srli_epi64(prev, 63));
+        let from_right = _mm256_or_si256(_mm256_srli_epi64(cur, 1),
+            _mm256_slli_epi64(after, 63));
+        _mm256_storeu_si256(next[idx..].as_mut_ptr() as *mut __m256i,
+            _mm256_xor_si256(from_left, from_right));
+
+        idx += 4;
+    }
+
+    idx
+}
+
+// Safe tiles in `num_rows` rows starting with `first_row`.
+fn count_safe_tiles(first_row: &Row, num_rows: usize, use_simd: bool) -> u64 {
+    let mut row = BitRow::from_row(first_row);
+    let mut next = BitRow::new(row.width);
+    let mut safe_tiles = 0;
+
+    for _ in 0..num_rows {
+        safe_tiles += row.num_safe() as u64;
+        row.next_row(&mut next, use_simd);
+        std::mem::swap(&mut row, &mut next);
+    }
+
+    safe_tiles
+}
+
+// Row `start` is the first one to repeat, and it comes back every `period`
+// rows after that.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Cycle {
+    start: u64,
+    period: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct SafeTileReport {
+    // None if counting would take generating more rows than allowed.
+    safe_tiles: Option<u128>,
+    cycle: Cycle,
+}
+
+// The distinct prime factors of `n`, smallest first.
+fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut primes = vec![];
+    let mut factor = 2;
+    while factor * factor <= n {
+        if n.is_multiple_of(factor) {
+            primes.push(factor);
+            while n.is_multiple_of(factor) {
+                n /= factor;
+            }
+        }
+        factor += 1;
+    }
+    if n > 1 {
+        primes.push(n);
+    }
+
+    primes
+}
+
+// Finds where the rows start repeating without generating them. Rule 90 is
+// linear, and `BitRow::skip` can jump any number of rows ahead, so once two
+// jumps of 2^a and 2^b rows are known to be the same, every row from row 2^a
+// on repeats every 2^b - 2^a rows. The true period divides that, and dividing
+// out one prime factor at a time while the rows still repeat finds it.
+fn find_cycle(first_row: &Row) -> Result<Cycle, String> {
+    let row = BitRow::from_row(first_row);
+    let ring_len = row.ring_len();
+
+    // shifts[k] is the ring shift for jumping 2^k rows, and a shift and its
+    // negation make the same jump.
+    let mut shifts: Vec<usize> = vec![];
+    let mut shift = 1 % ring_len;
+    let (first_k, repeat_k) = loop {
+        let same_jump = shifts.iter()
+            .position(|&s| s == shift || s == (ring_len - shift) % ring_len);
+        if let Some(first_k) = same_jump {
+            break (first_k, shifts.len());
+        }
+        shifts.push(shift);
+        shift = shift * 2 % ring_len;
+    };
+    if repeat_k >= 64 {
+        return Err(format!("rows {} tiles wide take too long to repeat",
+            row.width));
+    }
+
+    let settled = row.skip(1 << first_k);
+    let mut period = (1u64 << repeat_k) - (1u64 << first_k);
+    for prime in prime_factors(period) {
+        while period.is_multiple_of(prime)
+            && settled.skip(period / prime) == settled {
+            period /= prime;
+        }
+    }
+
+    // Once a row is on the cycle every later row is too, so the first one
+    // can be found by bisection.
+    let (mut lo, mut hi) = (0, 1u64 << first_k);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let mid_row = row.skip(mid);
+        if mid_row.skip(period) == mid_row {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    Ok(Cycle { start: lo, period: period })
+}
+
+// Like `count_safe_tiles`, but `num_rows` can be far more than could ever be
+// generated: only the rows up to the end of the first cycle are, and the
+// rest of the count follows from the safe tiles over one cycle. The count is
+// left out if that would take more than `max_generated` rows.
+fn count_safe_tiles_cycled(first_row: &Row, num_rows: u64,
+    max_generated: u64) -> Result<SafeTileReport, String> {
+
+    let cycle = find_cycle(first_row)?;
+    let cycle_end = cycle.start + cycle.period;
+    let rows_needed = std::cmp::min(num_rows, cycle_end);
+    if rows_needed > max_generated {
+        return Ok(SafeTileReport { safe_tiles: None, cycle: cycle });
+    }
+
+    let rows_left = num_rows.saturating_sub(cycle.start);
+    let partial_end = cycle.start + rows_left % cycle.period;
+
+    let mut row = BitRow::from_row(first_row);
+    let mut next = BitRow::new(row.width);
+    let mut safe_tiles: u128 = 0;
+    let mut safe_before_start = 0;
+    let mut safe_before_partial_end = 0;
+
+    for row_idx in 0..rows_needed {
+        if row_idx == cycle.start {
+            safe_before_start = safe_tiles;
+        }
+        if row_idx == partial_end {
+            safe_before_partial_end = safe_tiles;
+        }
+
+        safe_tiles += row.num_safe() as u128;
+        row.next_row(&mut next, USE_SIMD);
+        std::mem::swap(&mut row, &mut next);
+    }
+
+    if num_rows > cycle_end {
+        let safe_in_cycle = safe_tiles - safe_before_start;
+        safe_tiles = safe_before_start
+            + safe_in_cycle * (rows_left / cycle.period) as u128
+            + (safe_before_partial_end - safe_before_start);
+    }
+
+    Ok(SafeTileReport { safe_tiles: Some(safe_tiles), cycle: cycle })
+}
+
 fn do_tests() {
     let test_row1 = str_to_row("^^..^.");
     assert_eq!(test_row1, vec![
@@ -104,24 +442,177 @@ fn do_tests() {
     assert_eq!(result, 3);
     let result = count_tile_type(&test_row2, Tile::Trap);
     assert_eq!(result, 7);
+
+    let bit_row = BitRow::from_row(&test_row2);
+    assert_eq!(bit_row.to_row(), test_row2);
+    assert_eq!(bit_row.num_safe(), 3);
+    assert_eq!(count_safe_tiles(&test_row2, 10, USE_SIMD), 38);
+
+    // Rows wide enough for both the AVX2 and word at a time paths, checked
+    // against the tile at a time rules.
+    for width in [1, 63, 64, 65, 200, 700].iter().cloned() {
+        let mut row: Row = (0..width)
+            .map(|idx| if (idx * 7 + idx / 3) % 5 < 2 {
+                Tile::Trap
+            } else {
+                Tile::Safe
+            })
+            .collect();
+        let mut scalar = BitRow::from_row(&row);
+        let mut simd = scalar.clone();
+        let mut next = BitRow::new(width);
+
+        for _ in 0..50 {
+            row = generate_next_row(&row);
+
+            scalar.next_row(&mut next, false);
+            std::mem::swap(&mut scalar, &mut next);
+            simd.next_row(&mut next, true);
+            std::mem::swap(&mut simd, &mut next);
+
+            assert_eq!(scalar.to_row(), row);
+            assert_eq!(simd, scalar);
+            assert_eq!(scalar.num_safe(), count_tile_type(&row, Tile::Safe));
+        }
+    }
+
+    for width in 1..=12 {
+        let row: Row = (0..width)
+            .map(|idx| if idx % 3 == 0 { Tile::Trap } else { Tile::Safe })
+            .collect();
+
+        let report = count_safe_tiles_cycled(&row, 2000, 2000).unwrap();
+        assert_eq!(report.safe_tiles,
+            Some(count_safe_tiles(&row, 2000, USE_SIMD) as u128));
+
+        // The first row to come back, found by generating rows.
+        let mut seen = vec![BitRow::from_row(&row)];
+        let mut next = BitRow::new(width);
+        let cycle = loop {
+            seen.last().unwrap().next_row(&mut next, USE_SIMD);
+            if let Some(start) = seen.iter().position(|row| *row == next) {
+                let start = start as u64;
+                break Cycle { start: start, period: seen.len() as u64 - start };
+            }
+            seen.push(next.clone());
+        };
+        assert_eq!(report.cycle, cycle);
+
+        let cycle_start = cycle.start as usize;
+        let cycle_end = cycle_start + cycle.period as usize;
+        let safe_in_cycle = count_safe_tiles(&row, cycle_end, USE_SIMD)
+            - count_safe_tiles(&row, cycle_start, USE_SIMD);
+
+        let huge = 1_000_000_000_000_000_000;
+        let before = count_safe_tiles_cycled(&row, huge, 2000).unwrap();
+        let after = count_safe_tiles_cycled(&row, huge + cycle.period, 2000)
+            .unwrap();
+        assert_eq!(after.safe_tiles.unwrap() - before.safe_tiles.unwrap(),
+            safe_in_cycle as u128);
+    }
+
+    // Skipping ahead agrees with generating the rows, at widths where the
+    // ring shifts wrap around.
+    for width in [1, 5, 31, 100, 130].iter().cloned() {
+        let row: Row = (0..width)
+            .map(|idx| if (idx * idx + 3) % 7 < 3 {
+                Tile::Trap
+            } else {
+                Tile::Safe
+            })
+            .collect();
+        let first = BitRow::from_row(&row);
+        let mut generated = first.clone();
+        let mut next = BitRow::new(width);
+        for num_rows in 0..600 {
+            assert_eq!(first.skip(num_rows), generated);
+            generated.next_row(&mut next, USE_SIMD);
+            std::mem::swap(&mut generated, &mut next);
+        }
+    }
+
+    // A 100-tile row takes far too long to repeat to find the cycle by
+    // generating rows, so check the one found by skipping.
+    let wide_row = str_to_row(&".^".repeat(50));
+    let cycle = find_cycle(&wide_row).unwrap();
+    let first = BitRow::from_row(&wide_row).skip(cycle.start);
+    assert_eq!(first.skip(cycle.period), first);
+    assert_ne!(first.skip(cycle.period / 2), first);
+    assert_eq!(prime_factors(2 * 3 * 3 * 101), vec![2, 3, 101]);
+
+    let cells = row_to_cells(&test_row2);
+    let gens = TRAP_RULES.run(&cells, 10);
+    assert_eq!(gens.len(), 10);
+    assert_eq!(gens[9], row_to_cells(&str_to_row("^^.^^^..^^")));
+    assert_eq!(automaton::render_text(&gens[..2], '^', '.'),
+        ".^^.^.^^^^\n^^^...^..^\n");
+
+    // Rule 30 from a single live cell, the usual way to show it off.
+    let mut cells = vec![false; 7];
+    cells[3] = true;
+    let rule_30 = Automaton::new(30, Boundary::Dead);
+    assert_eq!(automaton::render_text(&rule_30.run(&cells, 3), '#', '.'),
+        "...#...\n..###..\n.##..#.\n");
+
+    let wrap = Automaton::new(90, Boundary::Wrap);
+    assert_eq!(wrap.step(&[true, false, false, false]),
+        vec![false, true, false, true]);
+    let reflect = Automaton::new(90, Boundary::Reflect);
+    assert_eq!(reflect.step(&[true, false, false, false]),
+        vec![true, true, false, false]);
+    let identity = Automaton::new(204, Boundary::Wrap);
+    assert_eq!(identity.step(&[true, false, true]), vec![true, false, true]);
+
+    let image = automaton::render_pbm(&[
+        vec![true, false, false, false, false, false, false, false, true],
+        vec![false; 9],
+    ]);
+    assert_eq!(image, b"P4\n9 2\n\x80\x80\x00\x00".to_vec());
+
+    let report = count_safe_tiles_cycled(&test_row2, 10, 100).unwrap();
+    assert_eq!(report.safe_tiles, Some(38));
+    let report = count_safe_tiles_cycled(&test_row2, 100, 5).unwrap();
+    assert_eq!(report.safe_tiles, None);
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt")
         .expect("Something went wrong reading the file");
 
+    const HUGE_ROW_COUNT: u64 = 1_000_000_000_000_000_000;
     const ROW_COUNT: usize = 40;
 
     do_tests();
 
-    let mut row = str_to_row(input.trim());
-    let mut safe_tiles = count_tile_type(&row, Tile::Safe);
-
-    count_tile_type(&row, Tile::Trap);
-    (0..(ROW_COUNT - 1)).for_each(|_| {
-        row = generate_next_row(&row);
-        safe_tiles += count_tile_type(&row, Tile::Safe);
-    });
+    let row = str_to_row(input.trim());
+    let safe_tiles = count_safe_tiles(&row, ROW_COUNT, USE_SIMD);
 
     println!("safe tiles: {}", safe_tiles);
+
+    let gens = TRAP_RULES.run(&row_to_cells(&row),
+        std::cmp::min(ROW_COUNT, IMAGE_ROWS));
+    if PRINT_ROWS {
+        print!("{}", automaton::render_text(&gens, '^', '.'));
+    }
+    fs::write(IMAGE_FILE, automaton::render_pbm(&gens))
+        .expect("Something went wrong writing the image");
+    println!("wrote {}", IMAGE_FILE);
+
+    if !COUNT_HUGE {
+        return;
+    }
+
+    match count_safe_tiles_cycled(&row, HUGE_ROW_COUNT, MAX_GENERATED_ROWS) {
+        Ok(report) => {
+            println!("rows repeat from row {} every {} rows",
+                report.cycle.start, report.cycle.period);
+            match report.safe_tiles {
+                Some(safe_tiles) => println!("safe tiles in {} rows: {}",
+                    HUGE_ROW_COUNT, safe_tiles),
+                None => println!("can't count {} rows: the first cycle ends \
+                    past row {}", HUGE_ROW_COUNT, MAX_GENERATED_ROWS),
+            }
+        }
+        Err(err) => println!("can't count {} rows: {}", HUGE_ROW_COUNT, err),
+    }
 }
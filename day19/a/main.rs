@@ -1,10 +1,19 @@
+#[path = "../../common/white_elephant.rs"]
+mod white_elephant;
+
 // use std::collections::HashSet;
 // use std::collections::LinkedList;
-// use std::fs;
+use std::env;
+use std::fs;
+use std::io;
 // use std::result::Result;
 // use std::str;
 // use std::str::FromStr;
 
+use white_elephant::StealRule;
+use white_elephant::TraceFormat;
+use white_elephant::TraceLimit;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 struct Elf {
     id: u32,
@@ -16,28 +25,20 @@ fn do_round(cur: &Vec<Elf>) -> Vec<Elf> {
         return cur.clone();
     }
 
-    let mut next = Vec::with_capacity(cur.len() / 2);
+    let mut next = Vec::with_capacity(cur.len() / 2 + 1);
+    for pair in cur.chunks(2) {
+        let thief = pair[0];
+        let victim = match pair.get(1) {
+            Some(victim) => *victim,
+            // An odd elf out robs the first elf, who by now also holds the
+            // presents of the second. The next round starts after the first.
+            None => next.remove(0),
+        };
 
-    if cur.len() & 1 == 0 {
-        for (i, elf) in cur.iter().enumerate() {
-            if i & 1 == 0 {
-                next.push(*elf);
-                next.last_mut().unwrap().num_presents +=
-                    cur[i + 1].num_presents;
-            }
-        }
-    } else {
-        for (i, elf) in cur[2..cur.len()].iter().enumerate() {
-            if i & 1 == 0 {
-                next.push(*elf);
-                next.last_mut().unwrap().num_presents +=
-                    if i == cur.len() - 3 {
-                        cur[0].num_presents + cur[1].num_presents
-                    } else {
-                        cur[i + 3].num_presents
-                    };
-            }
-        }
+        next.push(Elf {
+            id: thief.id,
+            num_presents: thief.num_presents + victim.num_presents,
+        });
     }
 
     next
@@ -60,6 +61,124 @@ fn do_tests() {
     assert_eq!(result, vec![
         Elf { id: 3, num_presents: 5 },
     ]);
+
+    assert_eq!(white_elephant::steal_from_left_winner(0), None);
+    for num_elves in 1..=300 {
+        let mut elves: Vec<Elf> = (1..=num_elves)
+            .map(|id| Elf { id: id, num_presents: 1 }).collect();
+        while elves.len() > 1 {
+            elves = do_round(&elves);
+        }
+
+        assert_eq!(white_elephant::steal_from_left_winner(num_elves as u64),
+            Some(elves[0].id as u64));
+
+        let party = white_elephant::play(num_elves as usize,
+            &white_elephant::StealFromLeft).unwrap();
+        assert_eq!(party.winner, elves[0].id as u64);
+        assert_eq!(party.presents[party.winner as usize - 1],
+            elves[0].num_presents as u64);
+    }
+
+    let party = white_elephant::play(5, &white_elephant::StealFromLeft)
+        .unwrap();
+    let victims: Vec<u64> = party.steals.iter()
+        .map(|steal| steal.victim)
+        .collect();
+    assert_eq!(victims, vec![2, 4, 1, 5]);
+    assert_eq!(party.steals[2], white_elephant::Steal {
+        round: 1,
+        thief: 5,
+        victim: 1,
+        num_presents: 2,
+    });
+    assert_eq!(party.presents, vec![2, 1, 5, 1, 3]);
+    assert!(white_elephant::play(0, &white_elephant::StealFromLeft).is_none());
+
+    struct StealFromRight;
+    impl StealRule for StealFromRight {
+        fn victim(&self, cur_idx: usize, circle_size: usize) -> usize {
+            (cur_idx + circle_size - 1) % circle_size
+        }
+    }
+    let party = white_elephant::play(5, &StealFromRight).unwrap();
+    assert_eq!(party.winner, 4);
+
+    let trace_str = |num_elves, format, limit| {
+        let mut out = vec![];
+        let num_written = white_elephant::write_trace(num_elves,
+            &white_elephant::StealFromLeft, format, limit, &mut out).unwrap();
+        (num_written, String::from_utf8(out).unwrap())
+    };
+
+    let (num_written, trace) = trace_str(5, TraceFormat::Csv,
+        TraceLimit::default());
+    assert_eq!(num_written, 4);
+    assert_eq!(trace, concat!(
+        "round,thief,victim,num_presents\n",
+        "1,1,2,1\n",
+        "1,3,4,1\n",
+        "1,5,1,2\n",
+        "2,3,5,3\n"));
+
+    let (num_written, trace) = trace_str(5, TraceFormat::JsonLines,
+        TraceLimit { max_events: Some(0), until_robbed: None });
+    assert_eq!(num_written, 0);
+    assert_eq!(trace, "");
+
+    let (num_written, trace) = trace_str(5, TraceFormat::JsonLines,
+        TraceLimit { max_events: Some(1), until_robbed: None });
+    assert_eq!(num_written, 1);
+    assert_eq!(trace,
+        "{\"round\":1,\"thief\":1,\"victim\":2,\"num_presents\":1}\n");
+
+    let (num_written, _) = trace_str(5, TraceFormat::Csv,
+        TraceLimit { max_events: None, until_robbed: Some(1) });
+    assert_eq!(num_written, 3);
+
+    // The trace agrees with the engine about who leaves when.
+    let party = white_elephant::play(100, &white_elephant::StealFromLeft)
+        .unwrap();
+    let (_, trace) = trace_str(100, TraceFormat::Csv, TraceLimit::default());
+    let traced_victims: Vec<u64> = trace.lines().skip(1)
+        .map(|line| line.split(',').nth(2).unwrap().parse().unwrap())
+        .collect();
+    let victims: Vec<u64> = party.steals.iter()
+        .map(|steal| steal.victim)
+        .collect();
+    assert_eq!(traced_victims, victims);
+
+    // Custom rules get traced the same way.
+    let mut out = vec![];
+    white_elephant::write_trace(5, &StealFromRight, TraceFormat::Csv,
+        TraceLimit::default(), &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "round,thief,victim,num_presents\n",
+        "1,1,5,1\n",
+        "1,2,1,2\n",
+        "1,3,2,3\n",
+        "1,4,3,4\n"));
+
+    let args = |args: &[&str]| {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string())
+            .collect();
+        white_elephant::parse_trace_args(&args)
+    };
+    assert_eq!(args(&[]), Ok(None));
+    let trace_args = args(&["--trace", "out.jsonl", "--max-events", "10"])
+        .unwrap().unwrap();
+    assert_eq!(trace_args.format, TraceFormat::JsonLines);
+    assert_eq!(trace_args.limit,
+        TraceLimit { max_events: Some(10), until_robbed: None });
+    assert_eq!(args(&["--trace", "out.csv"]).unwrap().unwrap().format,
+        TraceFormat::Csv);
+    assert!(args(&["--until-robbed", "3"]).is_err());
+    assert!(args(&["--trace"]).is_err());
+    assert!(args(&["--trace", "out.csv", "--max-events", "x"]).is_err());
+
+    assert_eq!(white_elephant::steal_from_left_winner(u64::MAX),
+        Some(u64::MAX));
+    assert_eq!(white_elephant::steal_from_left_winner(1 << 63), Some(1));
 }
 
 fn main() {
@@ -67,16 +186,27 @@ fn main() {
 
     const INPUT: u32 = 3014603;
 
-    let mut elves: Vec<Elf> = (1..=INPUT)
-        .map(|id| Elf { id: id, num_presents: 1 }).collect();
-
-    while elves.len() > 1 {
-        elves = do_round(&elves);
+    match white_elephant::steal_from_left_winner(INPUT as u64) {
+        None => panic!("no elves in list!!"),
+        Some(winner) => println!("elf {} has {} presents", winner, INPUT),
     }
 
-    if elves.len() == 0 {
-        panic!("no elves in list!!");
-    }
+    let args: Vec<String> = env::args().skip(1).collect();
+    let trace_args = match white_elephant::parse_trace_args(&args) {
+        Ok(trace_args) => trace_args,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
 
-    println!("elf {} has {} presents", elves[0].id, elves[0].num_presents);
+    if let Some(trace_args) = trace_args {
+        let mut out = io::BufWriter::new(fs::File::create(&trace_args.file)
+            .expect("Something went wrong creating the trace file"));
+        let num_written = white_elephant::write_trace(INPUT as usize,
+            &white_elephant::StealFromLeft, trace_args.format,
+            trace_args.limit, &mut out)
+            .expect("Something went wrong writing the trace");
+        println!("wrote {} steals to {}", num_written, trace_args.file);
+    }
 }
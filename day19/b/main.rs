@@ -1,6 +1,11 @@
+#[path = "../../common/white_elephant.rs"]
+mod white_elephant;
+
 // use std::collections::HashSet;
 // use std::collections::LinkedList;
-// use std::fs;
+use std::env;
+use std::fs;
+use std::io;
 // use std::result::Result;
 // use std::str;
 // use std::str::FromStr;
@@ -47,6 +52,50 @@ fn do_tests() {
 
     let result = do_round(&result);
     assert_eq!(result, vec![23, 1, 4, 7, 10, 11, 12, 14, 17, 20]);
+
+    assert_eq!(white_elephant::steal_from_across_winner(0), None);
+    for num_elves in 1..=300 {
+        let mut elves: Vec<Elf> = (1..=num_elves).collect();
+        while elves.len() > 1 {
+            elves = do_round(&elves);
+        }
+
+        assert_eq!(white_elephant::steal_from_across_winner(num_elves as u64),
+            Some(elves[0] as u64));
+
+        let party = white_elephant::play(num_elves as usize,
+            &white_elephant::StealFromAcross).unwrap();
+        assert_eq!(party.winner, elves[0] as u64);
+    }
+
+    let party = white_elephant::play(5, &white_elephant::StealFromAcross)
+        .unwrap();
+    let victims: Vec<u64> = party.steals.iter()
+        .map(|steal| steal.victim)
+        .collect();
+    assert_eq!(victims, vec![3, 5, 1, 4]);
+    assert_eq!(party.winner, 2);
+    assert_eq!(party.presents[1], 5);
+
+    let mut out = vec![];
+    let num_written = white_elephant::write_trace(5,
+        &white_elephant::StealFromAcross, white_elephant::TraceFormat::Csv,
+        white_elephant::TraceLimit { max_events: None, until_robbed: Some(1) },
+        &mut out).unwrap();
+    assert_eq!(num_written, 3);
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "round,thief,victim,num_presents\n",
+        "1,1,3,1\n",
+        "1,2,5,1\n",
+        "1,4,1,2\n"));
+
+    // 3^40 is the largest power of 3 that fits in a u64.
+    let pow = 3u64.pow(40);
+    assert_eq!(white_elephant::steal_from_across_winner(pow), Some(pow));
+    assert_eq!(white_elephant::steal_from_across_winner(u64::MAX),
+        Some(u64::MAX - pow));
+    assert_eq!(white_elephant::steal_from_across_winner(2 * 3u64.pow(39) + 5),
+        Some(3u64.pow(39) + 10));
 }
 
 fn main() {
@@ -54,15 +103,27 @@ fn main() {
 
     const INPUT: u32 = 3014603;
 
-    let mut elves: Vec<Elf> = (1..=INPUT).map(|id| id).collect();
-
-    while elves.len() > 1 {
-        elves = do_round(&elves);
+    match white_elephant::steal_from_across_winner(INPUT as u64) {
+        None => panic!("no elves in list!!"),
+        Some(winner) => println!("elf {} has all the presents", winner),
     }
 
-    if elves.len() == 0 {
-        panic!("no elves in list!!");
-    }
+    let args: Vec<String> = env::args().skip(1).collect();
+    let trace_args = match white_elephant::parse_trace_args(&args) {
+        Ok(trace_args) => trace_args,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
 
-    println!("elf {} has all the presents", elves[0]);
+    if let Some(trace_args) = trace_args {
+        let mut out = io::BufWriter::new(fs::File::create(&trace_args.file)
+            .expect("Something went wrong creating the trace file"));
+        let num_written = white_elephant::write_trace(INPUT as usize,
+            &white_elephant::StealFromAcross, trace_args.format,
+            trace_args.limit, &mut out)
+            .expect("Something went wrong writing the trace");
+        println!("wrote {} steals to {}", num_written, trace_args.file);
+    }
 }
@@ -1,55 +1,107 @@
-// use std::collections::HashSet;
-// use std::collections::LinkedList;
 use std::fs;
-// use std::result::Result;
-// use std::str;
 use std::str::FromStr;
 
-struct IpRange {
-    lo: u32,
-    hi: u32,
-}
+#[path = "../../common/interval_set.rs"]
+mod interval_set;
 
-fn main() {
-    let input = fs::read_to_string("input.txt")
-        .expect("Something went wrong reading the file");
+use interval_set::{Interval, IntervalSet};
 
-    let mut ip_ranges: Vec<IpRange> = input.lines()
+fn parse_blacklist(input: &str) -> IntervalSet<u32> {
+    IntervalSet::from_ranges(input.lines()
         .map(|line| {
             let mut parts = line.trim().split("-");
-            IpRange {
-                lo: u32::from_str(parts.next().unwrap()).unwrap(),
-                hi: u32::from_str(parts.next().unwrap()).unwrap(),
-            }
-        })
-        .collect();
-
-    ip_ranges.sort_by(|a, b| {
-        if a.lo != b.lo {
-            a.lo.partial_cmp(&b.lo).unwrap()
-        } else {
-            a.hi.partial_cmp(&b.hi).unwrap()
+            Interval::new(
+                u32::from_str(parts.next().unwrap()).unwrap(),
+                u32::from_str(parts.next().unwrap()).unwrap())
+        }))
+}
+
+// Checks every set operation on u8 sets against plain bool arrays.
+fn check_against_arrays(a: &IntervalSet<u8>, b: &IntervalSet<u8>) {
+    let to_array = |set: &IntervalSet<u8>| {
+        let mut arr = [false; 256];
+        for val in 0..=255u8 {
+            arr[val as usize] = set.contains(val);
         }
-    });
+        arr
+    };
 
-    let mut result = None;
+    let arr_a = to_array(a);
+    let arr_b = to_array(b);
+    let union = to_array(&a.union(b));
+    let intersection = to_array(&a.intersection(b));
+    let difference = to_array(&a.difference(b));
+    let complement = to_array(&a.complement(Interval::new(10, 200)));
 
-    let mut candidate = 0;
-    for ip_range in ip_ranges {
-        if candidate < ip_range.lo {
-            result = Some(candidate);
-            break;
-        }
+    for i in 0..256 {
+        assert_eq!(union[i], arr_a[i] || arr_b[i]);
+        assert_eq!(intersection[i], arr_a[i] && arr_b[i]);
+        assert_eq!(difference[i], arr_a[i] && !arr_b[i]);
+        assert_eq!(complement[i], (10..=200).contains(&i) && !arr_a[i]);
+    }
+
+    assert_eq!(a.len(),
+        Some(arr_a.iter().filter(|&&x| x).count() as u128));
+    assert_eq!(a.lowest_free(Interval::full()),
+        (0..=255u8).find(|&val| !arr_a[val as usize]));
+}
+
+fn do_tests() {
+    let blacklist = parse_blacklist("5-8\n0-2\n4-7\n");
+    assert_eq!(blacklist.ranges(),
+        &[Interval::new(0, 2), Interval::new(4, 8)]);
+    assert_eq!(blacklist.lowest_free(Interval::new(0, 9)), Some(3));
+    assert_eq!(blacklist.lowest_free(Interval::new(4, 8)), None);
 
-        if ip_range.hi >= candidate {
-            candidate = ip_range.hi.wrapping_add(1);
-            if candidate == 0 {
-                break;
+    let full: IntervalSet<u32> =
+        IntervalSet::from_ranges(vec![Interval::full()]);
+    assert_eq!(full.lowest_free(Interval::full()), None);
+    assert_eq!(IntervalSet::<u32>::new().lowest_free(Interval::full()),
+        Some(0));
+
+    // Inserting one range at a time has to agree with merging them all at
+    // once, for ranges made up by a small linear congruential generator.
+    let mut seed: u32 = 12345;
+    let mut next_rand = || {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (seed >> 16) as u8
+    };
+    for _ in 0..200 {
+        let mut ranges_a = vec![];
+        let mut ranges_b = vec![];
+        for i in 0..(next_rand() % 8) {
+            let (x, y) = (next_rand(), next_rand());
+            let (lo, hi) = (x.min(y), x.max(y));
+            let range = Interval::new(lo, lo + (hi - lo) / 4);
+            if i % 2 == 0 {
+                ranges_a.push(range);
+            } else {
+                ranges_b.push(range);
             }
         }
+
+        let mut inserted = IntervalSet::new();
+        for range in &ranges_a {
+            inserted.insert(*range);
+        }
+        let a = IntervalSet::from_ranges(ranges_a);
+        assert_eq!(inserted, a);
+
+        let b = IntervalSet::from_ranges(ranges_b);
+        check_against_arrays(&a, &b);
+        check_against_arrays(&b, &a);
     }
+}
+
+fn main() {
+    do_tests();
+
+    let input = fs::read_to_string("input.txt")
+        .expect("Something went wrong reading the file");
+
+    let blacklist = parse_blacklist(&input);
 
-    match result {
+    match blacklist.lowest_free(Interval::full()) {
         None => println!("no IP found"),
         Some(ip) => println!("got IP: {}", ip),
     }
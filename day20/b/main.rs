@@ -1,61 +1,68 @@
-// use std::collections::HashSet;
-// use std::collections::LinkedList;
 use std::fs;
-// use std::result::Result;
-// use std::str;
 use std::str::FromStr;
 
-struct IpRange {
-    lo: u32,
-    hi: u32,
+#[path = "../../common/interval_set.rs"]
+mod interval_set;
+
+use interval_set::{Interval, IntervalSet};
+
+fn parse_blacklist(input: &str) -> IntervalSet<u32> {
+    IntervalSet::from_ranges(input.lines()
+        .map(|line| {
+            let mut parts = line.trim().split("-");
+            Interval::new(
+                u32::from_str(parts.next().unwrap()).unwrap(),
+                u32::from_str(parts.next().unwrap()).unwrap())
+        }))
+}
+
+fn do_tests() {
+    let blacklist = parse_blacklist("5-8\n0-2\n4-7\n");
+    assert_eq!(blacklist.num_free(Interval::new(0, 9)), Some(2));
+    assert_eq!(blacklist.num_gaps(Interval::new(0, 9)), 2);
+    assert_eq!(blacklist.num_free(Interval::new(3, 3)), Some(1));
+    assert_eq!(blacklist.num_free(Interval::new(4, 8)), Some(0));
+
+    let mut ends = IntervalSet::new();
+    ends.insert(Interval::new(u32::MAX, u32::MAX));
+    ends.insert(Interval::new(0, 0));
+    assert_eq!(ends.num_free(Interval::full()),
+        Some(u32::MAX as u128 - 1));
+    assert_eq!(ends.num_gaps(Interval::full()), 1);
+    ends.insert(Interval::new(1, u32::MAX - 1));
+    assert_eq!(ends.ranges(), &[Interval::full()]);
+
+    // The whole u64 range has one more value than a u64 can count.
+    let empty: IntervalSet<u64> = IntervalSet::new();
+    assert_eq!(empty.num_free(Interval::full()), Some(1 << 64));
+    let top = IntervalSet::from_ranges(vec![Interval::new(1, u64::MAX)]);
+    assert_eq!(top.complement(Interval::full()).ranges(),
+        &[Interval::new(0, 0)]);
+    assert_eq!(top.union(&top.complement(Interval::full())).len(),
+        Some(1 << 64));
+    assert!(top.difference(&top).is_empty());
+
+    // The whole u128 range is one more value than even a u128 can count.
+    let empty: IntervalSet<u128> = IntervalSet::new();
+    assert_eq!(empty.num_free(Interval::full()), None);
+    assert_eq!(empty.num_gaps(Interval::full()), 1);
+    let mut almost = IntervalSet::new();
+    almost.insert(Interval::new(0, 0));
+    assert_eq!(almost.num_free(Interval::full()), Some(u128::MAX));
+    almost.insert(Interval::new(1, u128::MAX));
+    assert_eq!(almost.lowest_free(Interval::full()), None);
+    assert_eq!(almost.len(), None);
 }
 
 fn main() {
+    do_tests();
+
     let input = fs::read_to_string("input.txt")
         .expect("Something went wrong reading the file");
 
-    let mut ip_ranges: Vec<IpRange> = input.lines()
-        .map(|line| {
-            let mut parts = line.trim().split("-");
-            IpRange {
-                lo: u32::from_str(parts.next().unwrap()).unwrap(),
-                hi: u32::from_str(parts.next().unwrap()).unwrap(),
-            }
-        })
-        .collect();
-
-    ip_ranges.sort_by(|a, b| {
-        if a.lo != b.lo {
-            a.lo.partial_cmp(&b.lo).unwrap()
-        } else {
-            a.hi.partial_cmp(&b.hi).unwrap()
-        }
-    });
-
-    let mut new_ranges = vec![];
-    for ip_range in ip_ranges {
-        if new_ranges.is_empty() {
-            new_ranges.push(ip_range);
-            continue;
-        }
-
-        let end = new_ranges.last_mut().unwrap();
-        if end.hi == std::u32::MAX {
-            break;
-        } else if ip_range.lo > end.hi + 1 {
-            new_ranges.push(ip_range);
-        } else if ip_range.hi > end.hi {
-            end.hi = ip_range.hi;
-        }
-    }
-
-    let ip_ranges = new_ranges;
-
-    let num_ips = (0..(ip_ranges.len() - 1))
-        .map(|i| ip_ranges[i + 1].lo - ip_ranges[i].hi - 1)
-        .sum::<u32>()
-        + ip_ranges.first().unwrap().lo
-        + (std::u32::MAX - ip_ranges.last().unwrap().hi);
+    let blacklist = parse_blacklist(&input);
 
+    // Any count of u32 values fits in a u128.
+    let num_ips = blacklist.num_free(Interval::full()).unwrap();
     println!("num allowed IPs: {}", num_ips);
 }
@@ -1,100 +1,125 @@
-use std::collections::HashMap;
-// use std::collections::HashSet;
-// use std::collections::LinkedList;
+#[path = "../../common/df.rs"]
+mod df;
+
 use std::fs;
-// use std::result::Result;
-// use std::str;
-use std::str::FromStr;
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-struct Coord {
-    x: u32,
-    y: u32,
-}
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct Node {
-    pos: Coord,
-    size: u32,
-    used: u32,
-    avail: u32,
+use df::Coord;
+use df::Node;
+use df::parse_nodes;
+
+// A viable pair is an ordered pair of different nodes (A, B) where A holds
+// some data and all of it would fit in B's free space.
+struct ViablePairs {
+    count: usize,
+    pairs: Option<Vec<(Coord, Coord)>>,
 }
 
-fn parse_node(s: &str) -> Node {
-    assert_eq!(s.split_whitespace().count(), 5);
+// Sorts the nodes by free space once, then binary searches for the first node
+// with room for each node's data, so counting takes O(n log n). Listing the
+// pairs costs an extra O(1) per pair on top of that.
+fn viable_pairs(nodes: &[Node], list_pairs: bool) -> ViablePairs {
+    let mut by_avail = nodes.to_vec();
+    by_avail.sort_by_key(|node| node.avail);
+
+    let mut count = 0;
+    let mut pairs = if list_pairs { Some(vec![]) } else { None };
+
+    for node_a in nodes.iter().filter(|node| node.used > 0) {
+        let first_fit = by_avail
+            .partition_point(|node| node.avail < node_a.used);
+        let fits_in_self = node_a.used <= node_a.avail;
+        count += by_avail.len() - first_fit - fits_in_self as usize;
+
+        if let Some(pairs) = pairs.as_mut() {
+            pairs.extend(by_avail[first_fit..].iter()
+                .filter(|node_b| node_b.pos != node_a.pos)
+                .map(|node_b| (node_a.pos, node_b.pos)));
+        }
+    }
+
+    ViablePairs {
+        count: count,
+        pairs: pairs,
+    }
+}
 
-    let val_from_size = |size_str: &str| {
-        u32::from_str(&size_str[..(size_str.len() - 1)]).unwrap()
+fn do_tests() {
+    // A small pseudo-random cluster, checked against comparing every pair.
+    let mut seed: u64 = 12345;
+    let mut next_val = |max: u64| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 33) % max
     };
+    let nodes: Vec<Node> = (0..200)
+        .map(|idx| {
+            let size = 10 + next_val(90);
+            let used = if next_val(10) == 0 { 0 } else { next_val(size + 1) };
+            Node {
+                pos: Coord { x: idx % 20, y: idx / 20 },
+                size: size,
+                used: used,
+                avail: size - used,
+            }
+        })
+        .collect();
 
-    let mut parts = s.split_whitespace();
-    let mut coord_parts = parts.next().unwrap().split("-");
-    coord_parts.next();
-    let x = u32::from_str(&coord_parts.next().unwrap()[1..]).unwrap();
-    let y = u32::from_str(&coord_parts.next().unwrap()[1..]).unwrap();
-    let size = val_from_size(parts.next().unwrap());
-    let used = val_from_size(parts.next().unwrap());
-    let avail = val_from_size(parts.next().unwrap());
-
-    Node {
-        pos: Coord { x: x, y: y },
-        size: size,
-        used: used,
-        avail: avail,
+    let mut brute_force = vec![];
+    for node_a in &nodes {
+        for node_b in &nodes {
+            if node_a.pos != node_b.pos && node_a.used > 0
+                && node_a.used <= node_b.avail {
+
+                brute_force.push((node_a.pos, node_b.pos));
+            }
+        }
     }
+
+    let result = viable_pairs(&nodes, true);
+    let mut pairs = result.pairs.unwrap();
+    assert_eq!(result.count, brute_force.len());
+    assert_eq!(pairs.len(), brute_force.len());
+    let pair_key = |pair: &(Coord, Coord)| (pair.0.y, pair.0.x, pair.1.y,
+        pair.1.x);
+    pairs.sort_by_key(pair_key);
+    brute_force.sort_by_key(pair_key);
+    assert_eq!(pairs, brute_force);
+
+    // Every node fits in every other one, far more pairs than fit in a u32.
+    let nodes: Vec<Node> = (0..300_000)
+        .map(|idx| Node {
+            pos: Coord { x: idx, y: 0 },
+            size: 2,
+            used: 1,
+            avail: 1,
+        })
+        .collect();
+    let result = viable_pairs(&nodes, false);
+    assert_eq!(result.count, 300_000 * 299_999);
+    assert!(result.pairs.is_none());
 }
 
 fn main() {
+    do_tests();
+
     let input = fs::read_to_string("input.txt")
         .expect("Something went wrong reading the file");
 
-    let mut lines = input.lines();
-    lines.next();
-    lines.next();
-
-    let mut min_x = std::u32::MAX;
-    let mut max_x = std::u32::MIN;
-    let mut min_y = std::u32::MAX;
-    let mut max_y = std::u32::MIN;
-    let nodes: HashMap<Coord, Node> = lines
-        .map(|line| {
-            let node = parse_node(line);
-            min_x = std::cmp::min(min_x, node.pos.x);
-            max_x = std::cmp::max(max_x, node.pos.x);
-            min_y = std::cmp::min(min_y, node.pos.y);
-            max_y = std::cmp::max(max_y, node.pos.y);
-            (node.pos, node)
-        })
-        .collect();
+    let nodes = match parse_nodes(&input) {
+        Ok(nodes) => nodes,
+        Err(err) => {
+            println!("bad input: {}", err);
+            return;
+        }
+    };
+
+    let max_x = nodes.keys().map(|pos| pos.x).max().unwrap();
+    let max_y = nodes.keys().map(|pos| pos.y).max().unwrap();
+
+    println!("min/max x: {}, {}", 0, max_x);
+    println!("min/max y: {}, {}", 0, max_y);
+
+    let nodes: Vec<Node> = nodes.values().cloned().collect();
+    let result = viable_pairs(&nodes, false);
 
-    println!("min/max x: {}, {}", min_x, max_x);
-    println!("min/max y: {}, {}", min_y, max_y);
-
-    let mut valid_pairs = 0;
-    (min_x..=max_x).for_each(|x_a| {
-        (min_y..=max_y).for_each(|y_a| {
-            (min_x..=max_x).for_each(|x_b| {
-                (min_y..=max_y).for_each(|y_b| {
-                    let coord_a = Coord { x: x_a, y: y_a };
-                    let coord_b = Coord { x: x_b, y: y_b };
-                    if coord_a == coord_b {
-                        return;
-                    }
-
-                    let node_a = nodes.get(&coord_a).unwrap();
-                    let node_b = nodes.get(&coord_b).unwrap();
-
-                    if node_a.used == 0 {
-                        return;
-                    }
-
-                    if node_a.used <= node_b.avail {
-                        valid_pairs += 1;
-                    }
-                });
-            });
-        });
-    });
-
-    println!("valid pairs: {}", valid_pairs);
+    println!("valid pairs: {}", result.count);
 }
@@ -1,46 +1,284 @@
-// NOTE: This problem is not solved by code other than printing the initial
-// state out to the terminal so you can inspect it and then do some math and
-// logic to get the answer. The code below can theoretically solve it, but not
-// practically because it's too much work.
+// Moves the data in the top-right node to the top-left one.
+//
+// Only one node in the cluster starts out empty. Every other node either
+// holds little enough data to fit in any of the ordinary nodes, or so much
+// that it can never move anywhere. Data only ever moves into the empty node,
+// so the cluster behaves like a sliding puzzle with a single hole and some
+// fixed walls. The search runs over (hole position, goal data position) pairs
+// instead of whole cluster states.
+
+#[path = "../../common/search.rs"]
+mod search;
+#[path = "../../common/render.rs"]
+mod render;
+#[path = "../../common/df.rs"]
+mod df;
 
 use std::collections::HashMap;
-use std::collections::HashSet;
-use std::collections::LinkedList;
+use std::fmt;
 use std::fs;
-// use std::result::Result;
-// use std::str;
-use std::str::FromStr;
-use std::time;
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
-struct Coord {
-    x: u8,
-    y: u8,
-}
+use std::thread;
+use std::time::Duration;
+
+use df::Coord;
+use df::Node;
+use df::format_size;
+use df::parse_nodes;
+use df::parse_size;
 
 const GOAL_COORD: Coord = Coord { x: 0, y: 0 };
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
-struct Node {
-    // pos: Coord,
-    // size: u32,
-    used: u8,
-    avail: u8,
+// Color each node of the printed map by how full it is.
+const SHOW_HEAT_MAP: bool = false;
+
+// Animate the solution one move at a time after printing it.
+const REPLAY: bool = false;
+const REPLAY_FRAME_MS: u64 = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum NodeKind {
+    Empty,
+    Movable,
+    Wall,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct SimState {
+    empty_pos: Coord,
     goal_data_pos: Coord,
-    num_moves: u32,
+}
+
+// A single data transfer, from the node at `from` into the empty node at
+// `to`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Move {
+    from: Coord,
+    to: Coord,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node-x{}-y{} -> node-x{}-y{}",
+            self.from.x, self.from.y, self.to.x, self.to.y)
+    }
+}
+
+struct Grid {
+    max_x: usize,
+    max_y: usize,
+    kinds: Vec<NodeKind>,
     nodes: Vec<Node>,
+    empty_pos: Coord,
 }
 
-fn coord_to_idx(coord: Coord, max_x: u8) -> usize {
-    (max_x as usize + 1) * (coord.y as usize) + (coord.x as usize)
+impl Grid {
+    // Splits the nodes into the single empty node, the walls whose data is
+    // too big to ever fit in it, and everything else. Fails if the cluster
+    // doesn't fit the sliding puzzle model: there must be exactly one empty
+    // node, and every movable node's data must fit into every other movable
+    // node once that node has been emptied.
+    fn from_nodes(nodes: &HashMap<Coord, Node>) -> Result<Grid, String> {
+        let max_x = nodes.keys().map(|pos| pos.x).max().ok_or("no nodes")?;
+        let max_y = nodes.keys().map(|pos| pos.y).max().ok_or("no nodes")?;
+
+        let mut empties = nodes.iter().filter(|(_, node)| node.used == 0);
+        let (empty_pos, empty_node) = match (empties.next(), empties.next()) {
+            (Some((pos, node)), None) => (*pos, *node),
+            (None, _) => return Err("no empty node".to_string()),
+            (Some(_), Some(_)) => {
+                return Err("more than one empty node".to_string());
+            }
+        };
+
+        let mut kinds = Vec::with_capacity(nodes.len());
+        let mut grid_nodes = Vec::with_capacity(nodes.len());
+        let mut max_movable_used = 0;
+        let mut min_movable_size = empty_node.size;
+        for y in 0..=max_y {
+            for x in 0..=max_x {
+                let pos = Coord { x: x, y: y };
+                let node = nodes.get(&pos)
+                    .ok_or(format!("missing node x{}-y{}", x, y))?;
+
+                let kind = if pos == empty_pos {
+                    NodeKind::Empty
+                } else if node.used > empty_node.size {
+                    NodeKind::Wall
+                } else {
+                    max_movable_used = std::cmp::max(max_movable_used,
+                        node.used);
+                    min_movable_size = std::cmp::min(min_movable_size,
+                        node.size);
+                    NodeKind::Movable
+                };
+                kinds.push(kind);
+                grid_nodes.push(*node);
+            }
+        }
+
+        if max_movable_used > min_movable_size {
+            return Err(format!("movable data of {} doesn't fit in the \
+                smallest movable node of {}", format_size(max_movable_used),
+                format_size(min_movable_size)));
+        }
+
+        let grid = Grid {
+            max_x: max_x,
+            max_y: max_y,
+            kinds: kinds,
+            nodes: grid_nodes,
+            empty_pos: empty_pos,
+        };
+
+        if grid.kind_at(Coord { x: max_x, y: 0 }) == NodeKind::Wall {
+            return Err("the goal data can't be moved".to_string());
+        }
+
+        Ok(grid)
+    }
+
+    fn kind_at(&self, pos: Coord) -> NodeKind {
+        self.kinds[coord_to_idx(pos, self.max_x)]
+    }
+
+    fn start_state(&self) -> SimState {
+        SimState {
+            empty_pos: self.empty_pos,
+            goal_data_pos: Coord { x: self.max_x, y: 0 },
+        }
+    }
 }
 
-fn get_adjecent_coords(
-    pos: Coord, min_x: u8, min_y: u8, max_x: u8, max_y: u8) -> Vec<Coord> {
+// Draws the cluster the way the puzzle does: `_` for the empty node, `#` for
+// walls, `G` for the goal data and `.` for everything else, with the target
+// node in parentheses. `usage`, if given, is how full each node currently is
+// and colors the map as a heat map from blue (empty) to red (full).
+fn render_map(grid: &Grid, state: SimState, usage: Option<&[Node]>) -> String {
+    let mut out = String::new();
+
+    for y in 0..=grid.max_y {
+        let mut line = String::new();
+        for x in 0..=grid.max_x {
+            let pos = Coord { x: x, y: y };
+            let symbol = if pos == state.goal_data_pos {
+                'G'
+            } else if pos == state.empty_pos {
+                '_'
+            } else if grid.kind_at(pos) == NodeKind::Wall {
+                '#'
+            } else {
+                '.'
+            };
+
+            let cell = if pos == GOAL_COORD {
+                format!("({})", symbol)
+            } else {
+                format!(" {} ", symbol)
+            };
+
+            match usage {
+                Some(usage) => {
+                    let node = usage[coord_to_idx(pos, grid.max_x)];
+                    let [red, green, blue] =
+                        render::heat_color(node.used, node.size);
+                    line.push_str(&format!("\x1b[48;2;{};{};{}m{}\x1b[0m",
+                        red, green, blue, cell));
+                }
+                None => line.push_str(&cell),
+            }
+        }
+
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    out
+}
+
+// Moves all of the data in `mv.from` into `mv.to`, as the cluster would.
+fn apply_move(usage: &mut [Node], max_x: usize, mv: Move) {
+    let from_idx = coord_to_idx(mv.from, max_x);
+    let to_idx = coord_to_idx(mv.to, max_x);
+    let data = usage[from_idx].used;
+
+    usage[to_idx].used += data;
+    usage[to_idx].avail -= data;
+    usage[from_idx].used = 0;
+    usage[from_idx].avail = usage[from_idx].size;
+}
+
+// Redraws the cluster in place after each move of a solution.
+fn replay(grid: &Grid, moves: &[Move], heat: bool) {
+    let mut state = grid.start_state();
+    let mut usage = grid.nodes.clone();
+
+    for move_num in 0..=moves.len() {
+        if move_num > 0 {
+            let mv = moves[move_num - 1];
+            apply_move(&mut usage, grid.max_x, mv);
+            if mv.from == state.goal_data_pos {
+                state.goal_data_pos = mv.to;
+            }
+            state.empty_pos = mv.from;
+        }
+
+        // Clear the screen and home the cursor.
+        print!("\x1b[2J\x1b[H");
+        print!("{}", render_map(grid, state,
+            if heat { Some(&usage) } else { None }));
+        if move_num > 0 {
+            println!("move {}/{}: {}", move_num, moves.len(),
+                moves[move_num - 1]);
+        }
+
+        thread::sleep(Duration::from_millis(REPLAY_FRAME_MS));
+    }
+}
+
+impl search::Neighbors for Grid {
+    type Node = SimState;
+
+    // The only legal moves shift data from a non-wall neighbor of the empty
+    // node into it, which moves the empty node one step the other way.
+    fn neighbors(&self, cur_state: &SimState) -> Vec<SimState> {
+        get_adjecent_coords(cur_state.empty_pos, 0, 0, self.max_x, self.max_y)
+            .into_iter()
+            .filter(|pos| self.kind_at(*pos) != NodeKind::Wall)
+            .map(|pos| {
+                let goal_data_pos = if pos == cur_state.goal_data_pos {
+                    cur_state.empty_pos
+                } else {
+                    cur_state.goal_data_pos
+                };
+
+                SimState {
+                    empty_pos: pos,
+                    goal_data_pos: goal_data_pos,
+                }
+            })
+            .collect()
+    }
+}
+
+// The fewest transfers that bring the goal data to GOAL_COORD.
+fn solve(grid: &Grid) -> Option<Vec<Move>> {
+    let result = search::bfs(grid, grid.start_state(),
+        |state| state.goal_data_pos == GOAL_COORD);
+
+    let path = result.goal_path()?;
+    let moves = path.windows(2)
+        .map(|pair| Move { from: pair[1].empty_pos, to: pair[0].empty_pos })
+        .collect();
+
+    Some(moves)
+}
+
+fn coord_to_idx(coord: Coord, max_x: usize) -> usize {
+    (max_x + 1) * coord.y + coord.x
+}
+
+fn get_adjecent_coords(pos: Coord, min_x: usize, min_y: usize, max_x: usize,
+    max_y: usize) -> Vec<Coord> {
 
     let mut adj = Vec::with_capacity(4);
 
@@ -60,42 +298,6 @@ fn get_adjecent_coords(
     adj
 }
 
-fn parse_node(s: &str) -> (Node, Coord) {
-    assert_eq!(s.split_whitespace().count(), 5);
-
-    let u8_from_size = |size_str: &str| {
-        let cut_last = &size_str[..(size_str.len() - 1)];
-        let val_32 = u32::from_str(cut_last).unwrap();
-        let val_32 = std::cmp::min(val_32, 255);
-        val_32 as u8
-    };
-
-    let u8_from_coord = |coord_str: &str| {
-        u8::from_str(&coord_str[1..]).unwrap()
-    };
-
-    let mut parts = s.split_whitespace();
-    let mut coord_parts = parts.next().unwrap().split("-");
-    coord_parts.next();
-    let x = u8_from_coord(coord_parts.next().unwrap());
-    let y = u8_from_coord(coord_parts.next().unwrap());
-    // let size = val_from_size(parts.next().unwrap());
-    parts.next(); // skip the size
-    let used = u8_from_size(parts.next().unwrap());
-    let avail = u8_from_size(parts.next().unwrap());
-
-    let node = Node {
-        // pos: Coord { x: x, y: y },
-        // size: size,
-        used: used,
-        avail: avail,
-    };
-
-    let coord = Coord { x: x, y: y };
-
-    (node, coord)
-}
-
 fn do_tests() {
     let result = get_adjecent_coords(Coord { x: 1, y: 1 }, 0, 0, 2, 2);
     assert_eq!(result, vec![
@@ -139,6 +341,83 @@ fn do_tests() {
 
     let result = coord_to_idx(Coord { x: 0, y: 1 }, 2);
     assert_eq!(result, 3);
+
+    let sample = concat!(
+        "Filesystem            Size  Used  Avail  Use%\n",
+        "/dev/grid/node-x0-y0   10T    8T     2T   80%\n",
+        "/dev/grid/node-x0-y1   11T    6T     5T   54%\n",
+        "/dev/grid/node-x0-y2   32T   28T     4T   87%\n",
+        "/dev/grid/node-x1-y0    9T    7T     2T   77%\n",
+        "/dev/grid/node-x1-y1    8T    0T     8T    0%\n",
+        "/dev/grid/node-x1-y2   11T    7T     4T   63%\n",
+        "/dev/grid/node-x2-y0   10T    6T     4T   60%\n",
+        "/dev/grid/node-x2-y1    9T    8T     1T   88%\n",
+        "/dev/grid/node-x2-y2    9T    6T     3T   66%\n");
+    let grid = Grid::from_nodes(&parse_nodes(sample).unwrap()).unwrap();
+    assert_eq!(grid.kind_at(Coord { x: 0, y: 2 }), NodeKind::Wall);
+    assert_eq!(grid.kind_at(Coord { x: 1, y: 1 }), NodeKind::Empty);
+    assert_eq!(grid.kind_at(Coord { x: 2, y: 2 }), NodeKind::Movable);
+
+    assert_eq!(render_map(&grid, grid.start_state(), None), concat!(
+        "(.) .  G\n",
+        " .  _  .\n",
+        " #  .  .\n"));
+
+    let moves = solve(&grid).unwrap();
+    assert_eq!(moves.len(), 7);
+    assert_eq!(moves[0], Move {
+        from: Coord { x: 1, y: 0 },
+        to: Coord { x: 1, y: 1 },
+    });
+    assert_eq!(moves.last().unwrap().to, GOAL_COORD);
+
+    let mut usage = grid.nodes.clone();
+    apply_move(&mut usage, grid.max_x, moves[0]);
+    assert_eq!(usage[coord_to_idx(Coord { x: 1, y: 1 }, 2)].used,
+        parse_size("7T").unwrap());
+    assert_eq!(usage[coord_to_idx(Coord { x: 1, y: 0 }, 2)].avail,
+        parse_size("9T").unwrap());
+
+    // Two empty nodes break the single-hole model.
+    let two_holes = sample.replace(" 7T     4T   63%", " 0T    11T    0%");
+    assert!(Grid::from_nodes(&parse_nodes(&two_holes).unwrap()).is_err());
+
+    assert_eq!(parse_size("3T"), Ok(3 * 1024 * 1024));
+    assert_eq!(parse_size("512M"), Ok(512));
+    assert!(parse_size("12K").is_err());
+    assert_eq!(format_size(2048), "2G");
+
+    let mixed_units = sample.replace("/dev/grid/node-x2-y2    9T    6T     3T",
+        "/dev/grid/node-x2-y2    9T 6144G  3072G");
+    assert!(parse_nodes(&mixed_units).is_ok());
+
+    let check_err = |input: &str, line_num: Option<usize>| {
+        let err = parse_nodes(input).unwrap_err();
+        assert_eq!(err.line_num, line_num, "{}", err);
+    };
+    check_err(&sample.replace(" 8T     2T   80%", " 8T     3T   80%"), Some(2));
+    check_err(&sample.replace(" 6T     5T   54%", " 6T     5T   99%"), Some(3));
+    check_err(&sample.replace("87%", ""), Some(4));
+    check_err(&sample.replace("node-x2-y2", "node-x2-y1"), Some(10));
+    check_err(&sample.replace("node-x2-y2", "node-x2-z2"), Some(10));
+    check_err(&sample.replace("node-x2-y2", "node-x3-y2"), None);
+    check_err(&sample.replace("Filesystem", "Volume"), None);
+
+    let huge_x = sample.replace("node-x2-y2", "node-x99999999999999999999-y2");
+    let err = parse_nodes(&huge_x).unwrap_err();
+    assert_eq!(err.line_num, Some(10));
+    assert!(err.msg.contains("out of range"), "{}", err);
+
+    // Wider than the 255 columns a u8 coordinate could hold.
+    let mut wide = "Filesystem  Size  Used  Avail  Use%\n".to_string();
+    for x in 0..300 {
+        let (used, avail, pct) = if x == 150 { (0, 10, 0) } else { (6, 4, 60) };
+        wide.push_str(&format!("/dev/grid/node-x{}-y0  10T  {}T  {}T  {}%\n",
+            x, used, avail, pct));
+    }
+    let grid = Grid::from_nodes(&parse_nodes(&wide).unwrap()).unwrap();
+    assert_eq!((grid.max_x, grid.max_y), (299, 0));
+    assert_eq!(grid.kind_at(Coord { x: 150, y: 0 }), NodeKind::Empty);
 }
 
 fn main() {
@@ -147,273 +426,36 @@ fn main() {
     let input = fs::read_to_string("input.txt")
         .expect("Something went wrong reading the file");
 
-    let mut lines = input.lines();
-    lines.next();
-    lines.next();
-
-    let mut min_x = std::u8::MAX;
-    let mut max_x = std::u8::MIN;
-    let mut min_y = std::u8::MAX;
-    let mut max_y = std::u8::MIN;
-    let nodes: HashMap<Coord, Node> = lines
-        .map(|line| {
-            let (node, coord) = parse_node(line);
-            min_x = std::cmp::min(min_x, coord.x);
-            max_x = std::cmp::max(max_x, coord.x);
-            min_y = std::cmp::min(min_y, coord.y);
-            max_y = std::cmp::max(max_y, coord.y);
-            (coord, node)
-        })
-        .collect();
-
-    println!("min/max x: {}, {}", min_x, max_x);
-    println!("min/max y: {}, {}", min_y, max_y);
-
-    assert_eq!(min_x, 0);
-    assert_eq!(min_y, 0);
-
-    let start_state = {
-        let mut sim_state = SimState {
-            goal_data_pos: Coord { x: max_x, y: 0 },
-            num_moves: 0,
-            nodes: Vec::<Node>::with_capacity(nodes.len()),
-        };
-
-        (min_y..=max_y).for_each(|y| {
-            (min_x..=max_x).for_each(|x| {
-                let coord = Coord { x: x, y: y };
-                sim_state.nodes.push(*nodes.get(&coord).unwrap());
-            });
-        });
-
-        sim_state
-    };
-
-    // (min_y..=max_y).for_each(|y| {
-    //     (min_x..=max_x).for_each(|x| {
-    //         let coord = Coord { x: x, y: y };
-    //         let idx = coord_to_idx(coord, max_x);
-    //         let node = &start_state.nodes[idx];
-    //         print!("{}/{}  ", node.used, node.avail);
-    //     });
-
-    //     println!("");
-    // });
-
-    // println!("{:?}", start_state.nodes);
-
-
-    let mut visited_states = HashSet::<Vec<Node>>::new();
-    let mut state_queue = LinkedList::<SimState>::new();
-    state_queue.push_back(start_state);
-
-    let mut num_states_instpected = 0u64;
-
-    let now = time::Instant::now();
-    let ans = loop {
-        if state_queue.is_empty() {
-            break None;
-        }
-
-        let cur_state = state_queue.pop_front().unwrap();
-        if cur_state.goal_data_pos == GOAL_COORD {
-            break Some(cur_state.num_moves);
+    let nodes = match parse_nodes(&input) {
+        Ok(nodes) => nodes,
+        Err(err) => {
+            println!("bad input: {}", err);
+            return;
         }
-
-        (min_x..=max_x).for_each(|x| {
-            (min_y..=max_y).for_each(|y| {
-                let coord_a = Coord { x: x, y: y };
-                let idx_a = coord_to_idx(coord_a, max_x);
-                let node_a = &cur_state.nodes[idx_a];
-                if node_a.used == 0 {
-                    return;
-                }
-
-                for coord_b in get_adjecent_coords(
-                    coord_a, min_x, min_y, max_x, max_y) {
-
-                    let idx_b = coord_to_idx(coord_b, max_x);
-                    let node_b = &cur_state.nodes[idx_b];
-                    if node_a.used <= node_b.avail {
-                        let mut new_state = cur_state.clone();
-                        new_state.nodes[idx_b].used += node_a.used;
-                        new_state.nodes[idx_b].avail -= node_a.used;
-                        new_state.nodes[idx_a].used -= node_a.used;
-                        new_state.nodes[idx_a].avail += node_a.used;
-
-                        if coord_a == cur_state.goal_data_pos {
-                            new_state.goal_data_pos = coord_b;
-                        }
-
-                        new_state.num_moves += 1;
-
-                        if !visited_states.contains(&new_state.nodes) {
-                            visited_states.insert(new_state.nodes.clone());
-                            state_queue.push_back(new_state);
-                        }
-                    }
-                }
-            });
-        });
-
-        num_states_instpected += 1;
-        if num_states_instpected % 10000 == 0 {
-            let (min, sec) = {
-                let sec = now.elapsed().as_secs();
-                (sec / 60, sec % 60)
-            };
-
-            let mut num_empty = 0;
-            (min_x..=max_x).for_each(|x| {
-                (min_y..=max_y).for_each(|y| {
-                    let coord = Coord { x: x, y: y };
-                    let idx = coord_to_idx(coord, max_x);
-                    let node = &cur_state.nodes[idx];
-                    if node.used == 0 {
-                        num_empty += 1;
-                    }
-                });
-            });
-
-            println!(concat!(
-                "{} states seen in {}m{}s. move count: {}, num empty: {}, ",
-                "queue len: {}, visited len: {}"),
-                num_states_instpected, min, sec, cur_state.num_moves, num_empty,
-                state_queue.len(), visited_states.len());
+    };
+    let grid = match Grid::from_nodes(&nodes) {
+        Ok(grid) => grid,
+        Err(err) => {
+            println!("can't solve this cluster: {}", err);
+            return;
         }
     };
 
-    match ans {
+    println!("max x, y: {}, {}", grid.max_x, grid.max_y);
+    print!("{}", render_map(&grid, grid.start_state(),
+        if SHOW_HEAT_MAP { Some(&grid.nodes) } else { None }));
+
+    match solve(&grid) {
         None => println!("no solution"),
-        Some(num_moves) => println!("solution in {} moves", num_moves),
+        Some(moves) => {
+            for (move_num, mv) in moves.iter().enumerate() {
+                println!("{:4}: {}", move_num + 1, mv);
+            }
+            println!("solution in {} moves", moves.len());
+
+            if REPLAY {
+                replay(&grid, &moves, SHOW_HEAT_MAP);
+            }
+        }
     }
-
-
-
-
-    // // let mut max_avail_for_big_drives = 0;
-    // let mut min_used = std::u8::MAX;
-    // let mut valid_pairs = 0;
-    // // let mut empty_nodes = 0;
-    // let now = time::Instant::now();
-    // (min_x..=max_x).for_each(|x| {
-    //     (min_y..=max_y).for_each(|y| {
-
-
-    //         let coord_a = Coord { x: x, y: y };
-    //         let idx_a = coord_to_idx(coord_a, max_x);
-    //         let node_a = &start_state.nodes[idx_a];
-    //         if node_a.used == 0 {
-    //             return;
-    //         }
-    //         if node_a.used < min_used {
-    //             min_used = node_a.used;
-    //         }
-
-    //         for coord_b in get_adjecent_coords(
-    //             coord_a, min_x, min_y, max_x, max_y) {
-
-    //             let idx_b = coord_to_idx(coord_b, max_x);
-    //             let node_b = &start_state.nodes[idx_b];
-    //             if node_a.used <= node_b.avail {
-    //                 valid_pairs += 1;
-    //             }
-    //         }
-
-
-
-
-
-
-    //         // let coord_a = Coord { x: x_a, y: y_a };
-    //         // let node_a = nodes.get(&coord_a).unwrap();
-    //         // if node_a.used == 0 {
-    //         //     empty_nodes += 1;
-    //         // }
-
-
-    //         // let node_a = &nodes[x as usize][y as usize];
-    //         // // if node_a.size > 500 && node_a.avail > max_avail_for_big_drives {
-    //         // //     max_avail_for_big_drives = node_a.avail;
-    //         // // }
-    //         // if node_a.used == 0 {
-    //         //     return;
-    //         // }
-    //         // if node_a.used < min_used {
-    //         //     min_used = node_a.used;
-    //         // }
-
-    //         // for adj_coord in get_adjecent_coords(
-    //         //     Coord { x: x, y: y }, min_x, min_y, max_x, max_y) {
-
-    //         //     let node_b = &nodes[adj_coord.x as usize][adj_coord.y as usize];
-    //         //     if node_a.used <= node_b.avail {
-    //         //         valid_pairs += 1;
-    //         //     }
-    //         // }
-
-
-
-
-    //         // (min_x..=max_x).for_each(|x_b| {
-    //         //     (min_y..=max_y).for_each(|y_b| {
-    //         //         let coord_a = Coord { x: x_a, y: y_a };
-    //         //         let coord_b = Coord { x: x_b, y: y_b };
-    //         //         if coord_a == coord_b {
-    //         //             return;
-    //         //         }
-
-    //         //         let node_a = &nodes[x_a as usize][y_a as usize];
-    //         //         let node_b = &nodes[x_b as usize][y_b as usize];
-
-    //         //         if node_a.used == 0 {
-    //         //             return;
-    //         //         }
-
-    //         //         if node_a.used <= node_b.avail
-    //         //             && adjacent(node_a.pos, node_b.pos) {
-
-    //         //             valid_pairs += 1;
-    //         //         }
-    //         //     });
-    //         // });
-    //     });
-    // });
-
-    // assert_eq!(valid_pairs, 4);
-
-    // // println!("max_avail_for_big_drives: {}", max_avail_for_big_drives);
-    // println!("min_used: {}", min_used);
-    // println!("elapsed: {}", now.elapsed().as_secs_f32());
-
-    // println!("valid pairs: {}", valid_pairs);
-    // // println!("empty nodes: {}", empty_nodes);
-    // println!("Node size: {}", std::mem::size_of::<Node>());
-
-
-
-
-
-
-
-
-
-
-    // println!("\033[1;31mNode size: {}\033[0m;", std::mem::size_of::<Node>());
-
-    // // println!("This should be white.");
-    // // println!("\x1b[1;31mThis should be red.\x1b[0m");
-    // // println!("This should be white.");
-
-    // // println!("\x1B[6n\n");
-
-    // // let mut num = 0;
-    // // loop {
-    // //     use std::io::Write;
-
-    // //     print!("\x1b[6;3HHello {} ", num);
-    // //     num += 1;
-    // //     std::io::stdout().flush().unwrap();
-    // //     std::thread::sleep(std::time::Duration::from_secs(1));
-    // // }
 }
@@ -1,69 +1,126 @@
-use std::collections::HashSet;
-use std::collections::LinkedList;
+#[path = "../../common/search.rs"]
+mod search;
+#[path = "../../common/render.rs"]
+mod render;
+
+use std::collections::HashMap;
 use std::fs;
 // use std::result::Result;
 // use std::str;
-// use std::str::FromStr;
+use std::str::FromStr;
 
 type Map = Vec<Vec<u8>>;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct Coord {
-    x: u8,
-    y: u8,
+    x: u32,
+    y: u32,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-struct WorldState {
+// A checkpoint's label is its marker character on the map, or whatever the
+// legend below the map says that marker stands for.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Chkpt {
+    label: String,
     pos: Coord,
-    tagged: u16,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-struct SimState {
-    world_state: WorldState,
-    num_steps: u32,
+const START_LABEL: &str = "0";
+
+// Sets of checkpoints are usize bitmasks, so up to 64 checkpoints can be
+// tracked where a u16 allowed 16. Planning a route is the tighter limit:
+// Held-Karp needs a table entry for every subset of checkpoints, so its
+// memory doubles with each one added, and 20 checkpoints already take
+// about 100 MB.
+const MAX_PLANNED_CHKPTS: usize = 20;
+
+// Also plan a route through MAX_PLANNED_CHKPTS checkpoints in do_tests,
+// which needs the full 100 MB table.
+const RUN_STRESS_TESTS: bool = false;
+
+const IMAGE_FILE: &str = "route.ppm";
+const CELL_PIXELS: usize = 8;
+
+struct Ducts<'a> {
+    map: &'a Map,
 }
 
-fn parse_map(input_str: &str) -> (Map, Coord, u32) {
+// Any character other than '#' and '.' marks a checkpoint. The map may be
+// followed by a blank line and a legend with lines such as "a = 10", which
+// lets one map character stand for a longer label. The start, labeled 0,
+// comes first in the returned checkpoints and the rest follow in label order,
+// with numeric labels sorted by value and ahead of any others. Short lines
+// are padded with walls.
+fn parse_map(input_str: &str) -> Result<(Map, Vec<Chkpt>), String> {
+    let mut sections = input_str.trim().splitn(2, "\n\n");
+    let map_str = sections.next().unwrap();
+
+    let mut legend = HashMap::new();
+    for line in sections.next().unwrap_or("").lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (marker, label) = line.split_once('=')
+            .map(|(marker, label)| (marker.trim(), label.trim()))
+            .filter(|(marker, label)| marker.len() == 1 && !label.is_empty())
+            .ok_or(format!("bad legend line '{}'", line))?;
+        legend.insert(marker.as_bytes()[0], label.to_string());
+    }
 
-     let lines: Vec<Vec<u8>> = input_str.lines()
+    let lines: Vec<Vec<u8>> = map_str.lines()
         .map(|line| line.trim().bytes().collect::<Vec<u8>>())
         .collect();
 
-    let width = lines[0].len();
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
     let height = lines.len();
 
-    let mut map = vec![vec![0u8; height]; width];
-    let mut num_chkpts = 0;
+    let mut map = vec![vec![b'#'; height]; width];
+    let mut chkpts: Vec<Chkpt> = vec![];
 
-    let mut start_pos = Coord { x: 0, y: 0 };
     for (y, line) in lines.iter().enumerate() {
         for (x, c) in line.iter().enumerate() {
             map[x][y] = *c;
-            match *c {
-                 b'0' => {
-                    start_pos = Coord { x: x as u8, y: y as u8 };
-                    // map[x][y] = b'.';
-                },
-                b'1'..=b'9' => { num_chkpts += 1; },
-                _ => {}
+            if *c == b'#' || *c == b'.' {
+                continue;
             }
+
+            let label = match legend.get(c) {
+                Some(label) => label.clone(),
+                None => (*c as char).to_string(),
+            };
+            if chkpts.iter().any(|chkpt| chkpt.label == label) {
+                return Err(format!("checkpoint {} appears more than once",
+                    label));
+            }
+            chkpts.push(Chkpt {
+                label: label,
+                pos: Coord { x: x as u32, y: y as u32 },
+            });
         }
     }
 
+    chkpts.sort_by_key(|chkpt| {
+        let num = u64::from_str(&chkpt.label).ok();
+        (chkpt.label != START_LABEL, num.is_none(), num, chkpt.label.clone())
+    });
 
-    (map, start_pos, num_chkpts)
+    if chkpts.first().map(|chkpt| chkpt.label.as_str()) != Some(START_LABEL) {
+        return Err("no start checkpoint".to_string());
+    }
+
+    Ok((map, chkpts))
 }
 
 fn get_next_positions(pos: Coord, map: &Map) -> Vec<Coord> {
-    let width = map.len() as i32;
-    let height = map[0].len() as i32;
+    let width = map.len() as i64;
+    let height = map[0].len() as i64;
 
-    let pos_x = pos.x as i32;
-    let pos_y = pos.y as i32;
+    let pos_x = pos.x as i64;
+    let pos_y = pos.y as i64;
 
-    let test_coord = |x: i32, y: i32| -> bool {
+    let test_coord = |x: i64, y: i64| -> bool {
         if x < 0 || y < 0 || x >= width || y >= height {
             false
         } else {
@@ -71,8 +128,8 @@ fn get_next_positions(pos: Coord, map: &Map) -> Vec<Coord> {
         }
     };
 
-    let to_coord = |x: i32, y: i32| -> Coord {
-        Coord { x: x as u8, y: y as u8 }
+    let to_coord = |x: i64, y: i64| -> Coord {
+        Coord { x: x as u32, y: y as u32 }
     };
 
     let mut next_positions = vec![];
@@ -96,14 +153,273 @@ fn get_next_positions(pos: Coord, map: &Map) -> Vec<Coord> {
     next_positions
 }
 
-fn get_chkpt(pos: Coord, map: &Map) -> Option<u16> {
-    let c = map[pos.x as usize][pos.y as usize];
-    match c {
-        b'0'..=b'9' => Some((c - b'0') as u16),
-        _ => None
+impl search::Neighbors for Ducts<'_> {
+    type Node = Coord;
+
+    fn neighbors(&self, pos: &Coord) -> Vec<Coord> {
+        get_next_positions(*pos, self.map)
     }
 }
 
+// dists[a][b] is the number of steps between checkpoints a and b, found with
+// one flood fill from each checkpoint, or None if there's no way through.
+fn get_chkpt_dists(chkpt_positions: &[Coord], map: &Map)
+    -> Vec<Vec<Option<u32>>> {
+
+    let ducts = Ducts { map: map };
+
+    chkpt_positions.iter()
+        .map(|from_pos| {
+            let result = search::flood_fill(&ducts, *from_pos, None);
+            chkpt_positions.iter()
+                .map(|to_pos| result.dist_to(to_pos))
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Route {
+    len: u32,
+    // Checkpoint indexes in visiting order, starting with 0.
+    order: Vec<usize>,
+}
+
+// Held-Karp over the checkpoint distance matrix. For each set of visited
+// checkpoints, held as a bitmask, and each checkpoint `last` in it, the table
+// holds the shortest walk that starts at checkpoint 0, visits exactly that
+// set and ends at `last`, along with the checkpoint visited before `last` so
+// the order can be rebuilt.
+fn plan_route(dists: &[Vec<Option<u32>>], return_to_start: bool)
+    -> Result<Route, String> {
+
+    let num_chkpts = dists.len();
+    if num_chkpts > MAX_PLANNED_CHKPTS {
+        return Err(format!("{} checkpoints is more than the {} that can be \
+            planned", num_chkpts, MAX_PLANNED_CHKPTS));
+    }
+
+    const NO_WALK: u32 = u32::MAX;
+    let all_visited = (1usize << num_chkpts) - 1;
+    let entry_idx = |mask: usize, last: usize| mask * num_chkpts + last;
+
+    let mut best_lens = vec![NO_WALK; (all_visited + 1) * num_chkpts];
+    let mut prev_chkpts = vec![0u8; (all_visited + 1) * num_chkpts];
+    best_lens[entry_idx(1, 0)] = 0;
+
+    for mask in (1..=all_visited).filter(|mask| mask & 1 == 1) {
+        for last in 0..num_chkpts {
+            let len = best_lens[entry_idx(mask, last)];
+            if len == NO_WALK {
+                continue;
+            }
+
+            for (next, step) in dists[last].iter().enumerate() {
+                if mask & (1 << next) != 0 {
+                    continue;
+                }
+                let step = match step {
+                    Some(step) => *step,
+                    None => continue,
+                };
+
+                let next_idx = entry_idx(mask | (1 << next), next);
+                if len + step < best_lens[next_idx] {
+                    best_lens[next_idx] = len + step;
+                    prev_chkpts[next_idx] = last as u8;
+                }
+            }
+        }
+    }
+
+    let (mut last, len) = (0..num_chkpts)
+        .filter_map(|last| {
+            let len = best_lens[entry_idx(all_visited, last)];
+            if len == NO_WALK {
+                return None;
+            }
+            let back_len = if return_to_start { dists[last][0]? } else { 0 };
+            Some((last, len + back_len))
+        })
+        .min_by_key(|&(last, len)| (len, last))
+        .ok_or("some checkpoints can't be reached")?;
+
+    let mut order = vec![];
+    let mut mask = all_visited;
+    loop {
+        order.push(last);
+        if mask == 1 {
+            break;
+        }
+
+        let prev = prev_chkpts[entry_idx(mask, last)] as usize;
+        mask &= !(1 << last);
+        last = prev;
+    }
+    order.reverse();
+
+    if return_to_start {
+        order.push(0);
+    }
+
+    Ok(Route {
+        len: len,
+        order: order,
+    })
+}
+
+fn format_order(order: &[usize], chkpts: &[Chkpt]) -> String {
+    order.iter()
+        .map(|idx| chkpts[*idx].label.clone())
+        .collect::<Vec<String>>()
+        .join(" -> ")
+}
+
+// One leg of a route, with every position on it from the checkpoint it
+// leaves to the one it arrives at.
+struct Segment {
+    from: usize,
+    to: usize,
+    path: Vec<Coord>,
+}
+
+// Finds the steps behind each leg of `route` with a BFS between its ends.
+fn trace_route(route: &Route, chkpts: &[Chkpt], map: &Map) -> Vec<Segment> {
+    let ducts = Ducts { map: map };
+
+    route.order.windows(2)
+        .map(|leg| {
+            let to_pos = chkpts[leg[1]].pos;
+            let result = search::bfs(&ducts, chkpts[leg[0]].pos,
+                |pos| *pos == to_pos);
+
+            Segment {
+                from: leg[0],
+                to: leg[1],
+                path: result.goal_path().expect("planned leg has no path"),
+            }
+        })
+        .collect()
+}
+
+// Checkpoints are drawn as their place in the visiting order, 0-9 and then
+// a-z, since a label may not fit in one cell.
+fn visit_marker(visit_num: usize) -> char {
+    std::char::from_digit(visit_num as u32, 36).unwrap_or('*')
+}
+
+// ANSI background colors that the segments cycle through.
+const SEGMENT_COLORS: [u8; 6] = [41, 42, 43, 44, 45, 46];
+
+fn segment_color(seg_idx: usize) -> u8 {
+    SEGMENT_COLORS[seg_idx % SEGMENT_COLORS.len()]
+}
+
+// The map with each leg of the route drawn over it in its own color and
+// every checkpoint replaced by its visit number, followed by a key listing
+// each leg's checkpoints and length.
+fn render_ansi(map: &Map, chkpts: &[Chkpt], segments: &[Segment]) -> String {
+    let mut cell_colors = HashMap::new();
+    for (seg_idx, segment) in segments.iter().enumerate() {
+        for pos in &segment.path {
+            cell_colors.insert(*pos, segment_color(seg_idx));
+        }
+    }
+
+    let mut markers = HashMap::new();
+    if let Some(first) = segments.first() {
+        markers.insert(chkpts[first.from].pos, visit_marker(0));
+    }
+    for (seg_idx, segment) in segments.iter().enumerate() {
+        markers.entry(chkpts[segment.to].pos)
+            .or_insert(visit_marker(seg_idx + 1));
+    }
+
+    let mut s = String::new();
+    for y in 0..map[0].len() {
+        for (x, column) in map.iter().enumerate() {
+            let pos = Coord { x: x as u32, y: y as u32 };
+            let c = match markers.get(&pos) {
+                Some(marker) => *marker,
+                None if column[y] == b'#' => '#',
+                None => '.',
+            };
+
+            match cell_colors.get(&pos) {
+                Some(color) => s.push_str(&format!("\x1b[{};1m{}\x1b[0m",
+                    color, c)),
+                None => s.push(c),
+            }
+        }
+        s.push('\n');
+    }
+
+    for (seg_idx, segment) in segments.iter().enumerate() {
+        s.push_str(&format!("\x1b[{}m{}\x1b[0m {} -> {}: {} steps\n",
+            segment_color(seg_idx), visit_marker(seg_idx + 1),
+            chkpts[segment.from].label, chkpts[segment.to].label,
+            segment.path.len() - 1));
+    }
+
+    s
+}
+
+// Binary PPM (P6) image with each space drawn as a CELL_PIXELS square. Walls
+// are grey, open space is black, the legs of the route are shaded from blue
+// (first) to red (last) and checkpoints are white.
+fn render_ppm(map: &Map, chkpts: &[Chkpt], segments: &[Segment]) -> Vec<u8> {
+    let mut cell_colors = HashMap::new();
+    let last_seg = segments.len().saturating_sub(1) as u64;
+    for (seg_idx, segment) in segments.iter().enumerate() {
+        for pos in &segment.path {
+            cell_colors.insert(*pos,
+                render::heat_color(seg_idx as u64, last_seg));
+        }
+    }
+    for chkpt in chkpts {
+        cell_colors.insert(chkpt.pos, [255, 255, 255]);
+    }
+
+    render::render_ppm(map.len(), map[0].len(), CELL_PIXELS, |x, y| {
+        let pos = Coord { x: x as u32, y: y as u32 };
+        match cell_colors.get(&pos) {
+            Some(color) => *color,
+            None if map[x][y] == b'#' => [96, 96, 96],
+            None => [0, 0, 0],
+        }
+    })
+}
+
+// Plans a route along a 1000-tile corridor with the start at one end, a
+// checkpoint labeled Z at the other and the rest in between, labeled 1 up
+// through a legend.
+fn check_corridor(num_chkpts: usize) {
+    let markers = "abcdefghijklmnopqrstuvwxy";
+    let mut corridor = vec![b'.'; 1000];
+    corridor[0] = b'0';
+    let mut legend = String::new();
+    for (idx, marker) in markers.bytes().take(num_chkpts - 2).enumerate() {
+        corridor[50 * (idx + 1)] = marker;
+        legend.push_str(&format!("{} = {}\n", marker as char, idx + 1));
+    }
+    corridor[999] = b'Z';
+    let corridor_input = format!("{}\n\n{}",
+        String::from_utf8(corridor).unwrap(), legend);
+
+    let (map, chkpts) = parse_map(&corridor_input).unwrap();
+    assert_eq!(chkpts.len(), num_chkpts);
+    assert_eq!(chkpts[10].label, "10");
+    assert_eq!(chkpts[num_chkpts - 1].label, "Z");
+
+    let chkpt_positions: Vec<Coord> = chkpts.iter()
+        .map(|chkpt| chkpt.pos)
+        .collect();
+    let dists = get_chkpt_dists(&chkpt_positions, &map);
+    let route = plan_route(&dists, true).unwrap();
+    assert_eq!(route.len, 1998);
+    assert_eq!(route.order[1], num_chkpts - 1);
+}
+
 fn do_tests() {
     let sample_input = concat!(
         "###########\n",
@@ -111,7 +427,7 @@ fn do_tests() {
         "#.#######.#\n",
         "#4.......3#\n",
         "###########\n",);
-    let (sample_map, start_pos, num_chkpts) = parse_map(sample_input);
+    let (sample_map, chkpts) = parse_map(sample_input).unwrap();
     assert_eq!(sample_map, vec![
         vec![b'#', b'#', b'#', b'#', b'#'],
         vec![b'#', b'0', b'.', b'4', b'#'],
@@ -125,8 +441,12 @@ fn do_tests() {
         vec![b'#', b'2', b'.', b'3', b'#'],
         vec![b'#', b'#', b'#', b'#', b'#'],
     ]);
-    assert_eq!(start_pos, Coord { x: 1, y: 1 });
-    assert_eq!(num_chkpts, 4);
+    assert_eq!(chkpts.len(), 5);
+    assert_eq!(chkpts[0].pos, Coord { x: 1, y: 1 });
+    assert_eq!(chkpts[4], Chkpt {
+        label: "4".to_string(),
+        pos: Coord { x: 1, y: 3 },
+    });
 
     let result = get_next_positions(Coord { x: 1, y: 1}, &sample_map);
     assert_eq!(result, vec![
@@ -138,14 +458,65 @@ fn do_tests() {
         Coord { x: 9, y: 2 }, Coord { x: 8, y: 3 }
     ]);
 
-    let result = get_chkpt(Coord { x: 1, y: 1 }, &sample_map);
-    assert_eq!(result, Some(0));
+    let chkpt_positions: Vec<Coord> = chkpts.iter()
+        .map(|chkpt| chkpt.pos)
+        .collect();
+    let dists = get_chkpt_dists(&chkpt_positions, &sample_map);
+    assert_eq!(dists[0][4], Some(2));
+    assert_eq!(dists[1][3], Some(8));
+    assert_eq!(dists[2][2], Some(0));
+
+    let route = plan_route(&dists, true).unwrap();
+    assert_eq!(route.len, 20);
+    assert_eq!(route.order, vec![0, 4, 3, 2, 1, 0]);
+
+    let segments = trace_route(&route, &chkpts, &sample_map);
+    assert_eq!(segments.len(), route.order.len() - 1);
+    let traced_len: usize = segments.iter()
+        .map(|segment| segment.path.len() - 1)
+        .sum();
+    assert_eq!(traced_len as u32, route.len);
+    for segment in &segments {
+        assert_eq!(segment.path[0], chkpts[segment.from].pos);
+        for step in segment.path.windows(2) {
+            let next_positions = get_next_positions(step[0], &sample_map);
+            assert!(next_positions.contains(&step[1]));
+        }
+    }
 
-    let result = get_chkpt(Coord { x: 2, y: 1 }, &sample_map);
-    assert_eq!(result, None);
+    let rendered = render_ansi(&sample_map, &chkpts, &segments);
+    assert!(rendered.contains("0 -> 4: 2 steps"));
+    let image = render_ppm(&sample_map, &chkpts, &segments);
+    let (width, height) = (11 * CELL_PIXELS, 5 * CELL_PIXELS);
+    let header = format!("P6\n{} {}\n255\n", width, height);
+    assert!(image.starts_with(header.as_bytes()));
+    assert_eq!(image.len(), header.len() + width * height * 3);
+
+    let other_route = plan_route(&dists, false).unwrap();
+    assert_eq!(other_route.len, 14);
+
+    // A checkpoint walled off from the rest can't be part of any route.
+    let mut walled_dists = dists.clone();
+    for chkpt_dists in walled_dists.iter_mut() {
+        chkpt_dists[3] = None;
+    }
+    for dist in walled_dists[3].iter_mut() {
+        *dist = None;
+    }
+    walled_dists[3][3] = Some(0);
+    assert!(plan_route(&walled_dists, true).is_err());
+
+    assert!(parse_map("#0.1.1#").is_err());
+    assert!(parse_map("#1.2#").is_err());
+    assert!(parse_map("#0.a#\n\na is ten").is_err());
+
+    // A corridor too long for 8-bit coordinates with more checkpoints than
+    // fit in a 16-bit mask, some of them with multi-digit labels.
+    check_corridor(17);
 
-    let result = get_chkpt(Coord { x: 3, y: 1 }, &sample_map);
-    assert_eq!(result, Some(1));
+    if RUN_STRESS_TESTS {
+        check_corridor(MAX_PLANNED_CHKPTS);
+    }
 }
 
 fn main() {
@@ -154,66 +525,30 @@ fn main() {
     let input = fs::read_to_string("input.txt")
         .expect("Something went wrong reading the file");
 
-    let (map, start_pos, num_chkpts) = parse_map(&input);
-
-    const TARGET_TAGGED: u16 = std::u16::MAX;
-    const TARGET_TAGGED_EXCEPT_START: u16 = std::u16::MAX << 1;
-
-    let mut visited = HashSet::<WorldState>::new();
-    let mut states = LinkedList::<SimState>::new();
-
-    states.push_back(
-        SimState {
-            world_state: WorldState {
-                pos: start_pos,
-                tagged: (std::u16::MAX << (num_chkpts + 1)),
-            },
-            num_steps: 0,
-        });
-
-    let result = loop {
-        if states.is_empty() {
-            break None;
-        }
-
-        let cur = states.pop_front().unwrap();
-        if cur.world_state.tagged == TARGET_TAGGED {
-            break Some(cur.num_steps);
-        }
-
-        for next_pos in get_next_positions(cur.world_state.pos, &map) {
-
-            let new_tagged = match get_chkpt(next_pos, &map) {
-                Some(chkpt) => {
-                    let seen_all_chkpts =
-                        cur.world_state.tagged == TARGET_TAGGED_EXCEPT_START;
-                    if (chkpt == 0 && seen_all_chkpts) || (chkpt > 0) {
-                        cur.world_state.tagged | (1 << chkpt)
-                    } else {
-                        cur.world_state.tagged
-                    }
-                },
-                None => cur.world_state.tagged,
-            };
-
-            let new_ws = WorldState {
-                pos: next_pos,
-                tagged: new_tagged,
-            };
-
-            if !visited.contains(&new_ws) {
-                visited.insert(new_ws.clone());
-                states.push_back(
-                    SimState {
-                        world_state: new_ws,
-                        num_steps: cur.num_steps + 1,
-                    });
-            }
+    let (map, chkpts) = match parse_map(&input) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            println!("bad map: {}", err);
+            return;
         }
     };
 
-    match result {
-        None => println!("no solution"),
-        Some(num_steps) => println!("path len: {}", num_steps),
+    let chkpt_positions: Vec<Coord> = chkpts.iter()
+        .map(|chkpt| chkpt.pos)
+        .collect();
+    let dists = get_chkpt_dists(&chkpt_positions, &map);
+
+    match plan_route(&dists, true) {
+        Err(err) => println!("no solution: {}", err),
+        Ok(route) => {
+            println!("path len: {}", route.len);
+            println!("order: {}", format_order(&route.order, &chkpts));
+
+            let segments = trace_route(&route, &chkpts, &map);
+            print!("{}", render_ansi(&map, &chkpts, &segments));
+            fs::write(IMAGE_FILE, render_ppm(&map, &chkpts, &segments))
+                .expect("Something went wrong writing the image");
+            println!("wrote {}", IMAGE_FILE);
+        }
     };
 }